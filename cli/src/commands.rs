@@ -0,0 +1,479 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use iota_streams_lib::channels::channel_state::ChannelState;
+use iota_streams_lib::channels::{ChannelReader, ChannelWriter, MessageProof, verify_proof};
+use iota_streams_lib::payload::payload_serializers::{JsonPacket, JsonPacketBuilder, RawPacket};
+use iota_streams_lib::transport::channel_transport::ChannelTransport;
+use iota_streams_lib::transport::memory_transport::MemoryTransport;
+use iota_streams_lib::user_builders::network::Network;
+use iota_streams_lib::utility::iota_utility::{create_link, msg_index};
+use iota_streams_lib::utility::secrets::{SecretString, SymmetricKey};
+
+use crate::{Command, Opt, PayloadOpt, ReaderOpt};
+
+const PSW_ENV: &str = "STREAMS_PSW";
+const NEW_PSW_ENV: &str = "STREAMS_NEW_PSW";
+const KEY_ENV: &str = "STREAMS_KEY";
+const NONCE_ENV: &str = "STREAMS_NONCE";
+
+pub async fn run(opt: Opt) -> Result<()> {
+    let memory_file = opt.memory.clone();
+    let memory = match &memory_file {
+        Some(file) => Some(MemoryTransport::load(path_str(file)?)?),
+        None => None,
+    };
+    // The messages attached before an error are kept in the memory tangle too
+    let res = run_command(opt, memory.clone()).await;
+    if let (Some(file), Some(memory)) = (&memory_file, &memory) {
+        memory.save(path_str(file)?)?;
+    }
+    res
+}
+
+async fn run_command(opt: Opt, memory: Option<MemoryTransport>) -> Result<()> {
+    let network = network(&opt);
+    let node_url = node_url(&opt);
+    let node_url = node_url.as_deref();
+
+    match opt.cmd {
+        Command::Create { state, seed, save_on_tangle } => {
+            let psw = password(PSW_ENV, "Password: ")?;
+            let mut builder = ChannelWriter::builder().network(network);
+            if let Some(node) = node_url {
+                builder = builder.node(node);
+            }
+            if let Some(seed) = seed {
+                builder = builder.seed(&seed);
+            }
+            match memory {
+                Some(memory) => create(builder.build_with_transport(memory), &state, &psw, save_on_tangle).await,
+                None => create(builder.build(), &state, &psw, save_on_tangle).await,
+            }
+        }
+
+        Command::Send { state, public, masked, payload } => {
+            if public == "-" && masked.as_deref() == Some("-") {
+                return Err(anyhow::Error::msg("Only one of --public and --masked can be read from stdin"));
+            }
+            let public = read_input(&public)?;
+            let masked = match masked {
+                Some(masked) => read_input(&masked)?,
+                None => vec![],
+            };
+
+            let psw = password(PSW_ENV, "Password: ")?;
+            let state = path_str(&state)?;
+            match memory {
+                Some(memory) => {
+                    let channel_state = ChannelState::from_file(state, &psw)?;
+                    let channel = ChannelWriter::import_with_transport(&channel_state, &psw, memory).await?;
+                    send(channel, state, &psw, public, masked, &payload).await
+                }
+                None => {
+                    let channel = ChannelWriter::import_from_file(state, &psw, node_url, None).await?;
+                    send(channel, state, &psw, public, masked, &payload).await
+                }
+            }
+        }
+
+        Command::Read { reader, payload } => {
+            let psw = reader_psw(&reader)?;
+            match memory {
+                Some(memory) => {
+                    let mut channel = open_memory_reader(&reader, &psw, memory).await?;
+                    print_msgs(&mut channel, &payload).await?;
+                    save_reader(&channel, &reader, &psw).await
+                }
+                None => {
+                    let mut channel = open_reader(&reader, &psw, network, node_url).await?;
+                    print_msgs(&mut channel, &payload).await?;
+                    save_reader(&channel, &reader, &psw).await
+                }
+            }
+        }
+
+        Command::Follow { reader, payload, interval } => {
+            let psw = reader_psw(&reader)?;
+            match memory {
+                Some(memory) => follow(open_memory_reader(&reader, &psw, memory).await?, &reader, &psw, &payload, interval).await,
+                None => follow(open_reader(&reader, &psw, network, node_url).await?, &reader, &psw, &payload, interval).await,
+            }
+        }
+
+        Command::ExportState { state, out } => {
+            let psw = password(PSW_ENV, "Password: ")?;
+            let exported = ChannelState::from_file(path_str(&state)?, &psw)?.encrypt(&psw)?;
+            match out {
+                Some(out) => fs::write(out, &exported)?,
+                None => println!("{}", String::from_utf8_lossy(&exported)),
+            }
+            Ok(())
+        }
+
+        Command::ImportState { state, input } => {
+            let exported = read_input(&input)?;
+            let psw = password(PSW_ENV, "Password: ")?;
+            let channel_state = ChannelState::decrypt(String::from_utf8_lossy(&exported).trim().as_bytes(), &psw)?;
+            channel_state.write_to_file(path_str(&state)?, &psw)?;
+            println!("{}", serde_json::to_string_pretty(&channel_state.info())?);
+            Ok(())
+        }
+
+        Command::ChangePassword { state } => {
+            let psw = password(PSW_ENV, "Password: ")?;
+            let new_psw = password(NEW_PSW_ENV, "New password: ")?;
+            ChannelState::change_file_password(path_str(&state)?, &psw, &new_psw)
+        }
+
+        Command::InspectState { state, header_only } => {
+            match header_only {
+                false => {
+                    let psw = password(PSW_ENV, "Password: ")?;
+                    println!("{}", serde_json::to_string_pretty(&ChannelState::inspect(path_str(&state)?, &psw)?)?)
+                }
+                true => match ChannelState::read_file_header(path_str(&state)?)? {
+                    Some(channel_id) => println!("{}", json!({ "channel_address": channel_id })),
+                    None => return Err(anyhow::Error::msg("The state file has no header: inspect it with its password")),
+                },
+            }
+            Ok(())
+        }
+
+        Command::MsgIndex { channel, msg } => {
+            println!("{}", msg_index(&create_link(&channel, &msg)?));
+            Ok(())
+        }
+
+        Command::Verify { channel, author, proof } => {
+            if let Some(proof) = proof {
+                let proof = MessageProof::from_json(&fs::read_to_string(proof)?)?;
+                let author = author.ok_or_else(|| anyhow::Error::msg("The author is needed to verify a proof"))?;
+                let author_key = verify_proof(&proof, &author).await?;
+                println!("{}", json!({ "valid": true, "msg_id": proof.msg_id, "author": author_key }));
+                return Ok(());
+            }
+
+            let (channel_id, announce_id) = split_channel(&channel.unwrap_or_default())?;
+            let mut builder = ChannelReader::builder().network(network);
+            if let Some(node) = node_url {
+                builder = builder.node(node);
+            }
+            if let Some(author) = &author {
+                builder = builder.pin_author(author);
+            }
+            match memory {
+                Some(memory) => verify_chain(builder.build_with_transport(&channel_id, &announce_id, memory)).await,
+                None => verify_chain(builder.build(&channel_id, &announce_id)).await,
+            }
+        }
+    }
+}
+
+async fn create<Trans: ChannelTransport>(mut channel: ChannelWriter<Trans>, state: &Path, psw: &SecretString, save_on_tangle: bool) -> Result<()> {
+    if save_on_tangle {
+        let (channel_address, announce_id, state_msg_id) = channel.open_and_save(psw).await?;
+        println!("{}", json!({ "channel": channel_address, "announcement": announce_id, "state_msg": state_msg_id }));
+    } else {
+        let (channel_address, announce_id) = channel.open().await?;
+        println!("{}", json!({ "channel": channel_address, "announcement": announce_id }));
+    }
+    channel.export_to_file(psw, path_str(state)?).await
+}
+
+async fn send<Trans: ChannelTransport>(mut channel: ChannelWriter<Trans>, state: &str, psw: &SecretString, public: Vec<u8>, masked: Vec<u8>, payload: &PayloadOpt) -> Result<()> {
+    let key = symmetric_key(payload)?;
+    let msg_id = match payload.format.as_str() {
+        "raw" => channel.send_signed_raw_data(public, masked, key.as_ref()).await?,
+        _ => {
+            let public: Value = serde_json::from_slice(&public)?;
+            let masked: Value = match masked.is_empty() {
+                true => Value::Null,
+                false => serde_json::from_slice(&masked)?,
+            };
+            let mut builder = JsonPacketBuilder::new();
+            builder.public(&public)?.masked(&masked)?;
+            if let Some(key) = &key {
+                builder.key(key);
+            }
+            channel.send_signed_packet(&builder.build()).await?
+        }
+    };
+    println!("{}", json!({ "msg_id": msg_id, "msg_index": channel.msg_index(&msg_id)? }));
+    channel.export_to_file(psw, state).await
+}
+
+async fn follow<Trans: ChannelTransport>(mut channel: ChannelReader<Trans>, reader: &ReaderOpt, psw: &SecretString, payload: &PayloadOpt, interval: u64) -> Result<()> {
+    loop {
+        if print_msgs(&mut channel, payload).await? > 0 {
+            save_reader(&channel, reader, psw).await?;
+        }
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+async fn verify_chain<Trans: ChannelTransport>(reader: ChannelReader<Trans>) -> Result<()> {
+    let report = reader.verify_chain(None).await?;
+    println!("{}", report.to_json()?);
+    match report.is_valid() {
+        true => Ok(()),
+        false => Err(anyhow::Error::msg(format!("{} issues found in the chain", report.issues.len()))),
+    }
+}
+
+///
+/// Read a password from an environment variable or, if it is not set, from the terminal.
+/// The passwords are never taken as arguments, since they would be visible in the process list and in the shell history
+///
+fn password(env_var: &str, prompt: &str) -> Result<SecretString> {
+    match env::var(env_var) {
+        Ok(psw) => Ok(SecretString::from(psw)),
+        Err(_) => Ok(SecretString::from(rpassword::read_password_from_tty(Some(prompt))?)),
+    }
+}
+
+///
+/// The password of the reader is needed only when its state is kept in a file
+///
+fn reader_psw(opt: &ReaderOpt) -> Result<SecretString> {
+    match opt.reader_state {
+        Some(_) => password(PSW_ENV, "Password of the reader state: "),
+        None => Ok(SecretString::from("")),
+    }
+}
+
+fn network(opt: &Opt) -> Network {
+    if opt.local {
+        return Network::Local;
+    }
+    opt.network.clone()
+}
+
+///
+/// Node used to restore the states: without --node or --local the network saved in the state is used
+///
+fn node_url(opt: &Opt) -> Option<String> {
+    if opt.local {
+        return Network::Local.node_urls().first().cloned();
+    }
+    opt.node.clone()
+}
+
+async fn open_reader(opt: &ReaderOpt, psw: &SecretString, network: Network, node_url: Option<&str>) -> Result<ChannelReader> {
+    if let Some(state) = existing_state(opt) {
+        return ChannelReader::import_from_file(path_str(state)?, psw, node_url, None).await;
+    }
+
+    let (channel_id, announce_id) = split_channel(&opt.channel)?;
+    let mut builder = ChannelReader::builder().network(network);
+    if let Some(node) = node_url {
+        builder = builder.node(node);
+    }
+    attach(builder.build(&channel_id, &announce_id)).await
+}
+
+async fn open_memory_reader(opt: &ReaderOpt, psw: &SecretString, memory: MemoryTransport) -> Result<ChannelReader<MemoryTransport>> {
+    if let Some(state) = existing_state(opt) {
+        let channel_state = ChannelState::from_file(path_str(state)?, psw)?;
+        return ChannelReader::import_with_transport(&channel_state, psw, memory).await;
+    }
+
+    let (channel_id, announce_id) = split_channel(&opt.channel)?;
+    attach(ChannelReader::builder().build_with_transport(&channel_id, &announce_id, memory)).await
+}
+
+fn existing_state(opt: &ReaderOpt) -> Option<&PathBuf> {
+    opt.reader_state.as_ref().filter(|state| state.exists())
+}
+
+async fn attach<Trans: ChannelTransport>(mut reader: ChannelReader<Trans>) -> Result<ChannelReader<Trans>> {
+    reader.attach().await?;
+    Ok(reader)
+}
+
+async fn save_reader<Trans: ChannelTransport>(reader: &ChannelReader<Trans>, opt: &ReaderOpt, psw: &SecretString) -> Result<()> {
+    match &opt.reader_state {
+        Some(state) => reader.export_to_file(psw, path_str(state)?).await,
+        None => Ok(()),
+    }
+}
+
+async fn print_msgs<Trans: ChannelTransport>(reader: &mut ChannelReader<Trans>, payload: &PayloadOpt) -> Result<usize> {
+    let key = symmetric_key(payload)?;
+    let msgs = match payload.format.as_str() {
+        "raw" => {
+            let packets: Vec<(String, RawPacket)> = reader.fetch_parsed_msgs(key.as_ref()).await?;
+            packets.iter()
+                .map(|(id, packet)| json!({
+                    "msg_id": id,
+                    "public": packet.deserialize_public::<Vec<u8>>().map(|p| String::from_utf8_lossy(&p).to_string()).ok(),
+                    "masked": packet.deserialize_masked::<Vec<u8>>().map(|m| String::from_utf8_lossy(&m).to_string()).ok(),
+                }))
+                .collect::<Vec<Value>>()
+        }
+        _ => {
+            let packets: Vec<(String, JsonPacket)> = reader.fetch_parsed_msgs(key.as_ref()).await?;
+            packets.iter()
+                .map(|(id, packet)| json!({
+                    "msg_id": id,
+                    "public": packet.deserialize_public::<Value>().ok(),
+                    "masked": packet.deserialize_masked::<Value>().ok(),
+                }))
+                .collect::<Vec<Value>>()
+        }
+    };
+
+    for msg in &msgs {
+        println!("{}", msg);
+    }
+    Ok(msgs.len())
+}
+
+///
+/// The secrets of the key are read like the passwords, from the environment or from the terminal
+///
+fn symmetric_key(payload: &PayloadOpt) -> Result<Option<SymmetricKey>> {
+    if !payload.encrypted {
+        return Ok(None);
+    }
+    let key = password(KEY_ENV, "Key: ")?;
+    let nonce = password(NONCE_ENV, "Nonce: ")?;
+    Ok(Some(SymmetricKey::from_secrets(key.expose(), nonce.expose())))
+}
+
+fn split_channel(channel: &str) -> Result<(String, String)> {
+    let parts: Vec<&str> = channel.splitn(2, ':').collect();
+    match parts.as_slice() {
+        [channel_id, announce_id] => Ok((channel_id.to_string(), announce_id.to_string())),
+        _ => Err(anyhow::Error::msg(format!("Invalid channel, expected <channel_address>:<announcement_id>: {}", channel))),
+    }
+}
+
+fn read_input(input: &str) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    match input {
+        "-" => { io::stdin().read_to_end(&mut data)?; }
+        path => { data = fs::read(path)?; }
+    }
+    Ok(data)
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    match path.to_str() {
+        Some(path) => Ok(path),
+        None => Err(anyhow::Error::msg(format!("Invalid path: {}", path.display()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+    use tempfile::TempDir;
+
+    // The environment is shared by the tests running in parallel, so they all use the same secrets
+    fn set_secrets() {
+        env::set_var(PSW_ENV, "psw");
+        env::set_var(NEW_PSW_ENV, "new_psw");
+        env::set_var(KEY_ENV, "key");
+        env::set_var(NONCE_ENV, "nonce");
+    }
+
+    fn file(dir: &TempDir, name: &str) -> String {
+        dir.path().join(name).to_str().unwrap().to_string()
+    }
+
+    fn write_file(dir: &TempDir, name: &str, content: &str) -> String {
+        let path = file(dir, name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn memory(dir: &TempDir) -> MemoryTransport {
+        MemoryTransport::load(&file(dir, "tangle.json")).unwrap()
+    }
+
+    async fn run_args(dir: &TempDir, args: &[&str]) -> Result<()> {
+        set_secrets();
+        let tangle = file(dir, "tangle.json");
+        let mut cli_args = vec!["streams-cli", "--memory", tangle.as_str()];
+        cli_args.extend_from_slice(args);
+        run(Opt::from_iter(cli_args)).await
+    }
+
+    async fn create_channel(dir: &TempDir) -> (String, String) {
+        let state = file(dir, "channel.state");
+        run_args(dir, &["create", "--state", &state]).await.unwrap();
+        let info = ChannelState::inspect(&state, "psw").unwrap();
+        (state, format!("{}:{}", info.channel_address, info.announcement_id))
+    }
+
+    async fn send_value(dir: &TempDir, state: &str, value: &str) {
+        let public = write_file(dir, "public.json", &json!({ "value": value }).to_string());
+        run_args(dir, &["send", "--state", state, "--public", &public]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn sent_msgs_are_read_once() {
+        let dir = TempDir::new().unwrap();
+        let (state, channel) = create_channel(&dir).await;
+        send_value(&dir, &state, "first").await;
+
+        let reader_state = file(&dir, "reader.state");
+        run_args(&dir, &["read", "--channel", &channel, "--reader-state", &reader_state]).await.unwrap();
+        send_value(&dir, &state, "second").await;
+
+        let reader_state = ChannelState::from_file(&reader_state, "psw").unwrap();
+        let mut reader = ChannelReader::import_with_transport(&reader_state, "psw", memory(&dir)).await.unwrap();
+        let msgs: Vec<(String, JsonPacket)> = reader.fetch_parsed_msgs(None).await.unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].1.deserialize_public::<Value>().unwrap(), json!({ "value": "second" }));
+    }
+
+    #[tokio::test]
+    async fn masked_part_is_encrypted_with_the_secrets_of_the_environment() {
+        let dir = TempDir::new().unwrap();
+        let (state, channel) = create_channel(&dir).await;
+        let public = write_file(&dir, "public.json", "{}");
+        let masked = write_file(&dir, "masked.json", &json!({ "secret": 42 }).to_string());
+        run_args(&dir, &["send", "--state", &state, "--public", &public, "--masked", &masked, "--encrypted"]).await.unwrap();
+
+        let (channel_id, announce_id) = split_channel(&channel).unwrap();
+        let mut reader = attach(ChannelReader::builder().build_with_transport(&channel_id, &announce_id, memory(&dir))).await.unwrap();
+        let key = SymmetricKey::from_secrets("key", "nonce");
+        let msgs: Vec<(String, JsonPacket)> = reader.fetch_parsed_msgs(Some(&key)).await.unwrap();
+        assert_eq!(msgs[0].1.deserialize_masked::<Value>().unwrap(), json!({ "secret": 42 }));
+    }
+
+    #[tokio::test]
+    async fn two_stdin_inputs_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let (state, _) = create_channel(&dir).await;
+        assert!(run_args(&dir, &["send", "--state", &state, "--public", "-", "--masked", "-"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn password_is_changed_without_a_node() {
+        let dir = TempDir::new().unwrap();
+        let (state, _) = create_channel(&dir).await;
+        run_args(&dir, &["change-password", "--state", &state]).await.unwrap();
+        assert!(ChannelState::from_file(&state, "psw").is_err());
+        assert!(ChannelState::from_file(&state, "new_psw").is_ok());
+    }
+
+    #[tokio::test]
+    async fn chain_is_verified_against_the_author() {
+        let dir = TempDir::new().unwrap();
+        let (state, channel) = create_channel(&dir).await;
+        send_value(&dir, &state, "first").await;
+
+        run_args(&dir, &["verify", "--channel", &channel]).await.unwrap();
+        assert!(run_args(&dir, &["verify", "--channel", &channel, "--author", "00"]).await.is_err());
+    }
+}
@@ -0,0 +1,169 @@
+mod commands;
+
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+use iota_streams_lib::user_builders::network::Network;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "streams-cli",
+    about = "Manage IOTA Streams channels from the command line",
+    after_help = "The password of the states is read from STREAMS_PSW, and the new one of change-password from STREAMS_NEW_PSW; \
+    with --encrypted the secrets of the masked part are read from STREAMS_KEY and STREAMS_NONCE. If they are not set they are asked on the terminal"
+)]
+pub struct Opt {
+    /// Url of the node; it replaces the default nodes of the network
+    #[structopt(long, global = true)]
+    pub node: Option<String>,
+
+    /// Network of the node: mainnet, testnet, devnet or local
+    #[structopt(long, global = true, default_value = "testnet", parse(try_from_str = parse_network))]
+    pub network: Network,
+
+    /// Use the private tangle of a node running on the local machine, for testing
+    #[structopt(long, global = true)]
+    pub local: bool,
+
+    /// Keep the messages in a local file instead of attaching them to a node, for testing
+    #[structopt(long, global = true, parse(from_os_str))]
+    pub memory: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Create and announce a new channel, saving its state in a file
+    Create {
+        /// File where the channel state is saved
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// Seed of the author; a random one is generated if missing
+        #[structopt(long)]
+        seed: Option<String>,
+        /// Publish the encrypted state on the tangle too
+        #[structopt(long)]
+        save_on_tangle: bool,
+    },
+    /// Send a signed packet, reading its public and masked parts from files or from stdin
+    Send {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// File with the public part; `-` reads it from stdin
+        #[structopt(long, default_value = "-")]
+        public: String,
+        /// File with the masked part
+        #[structopt(long)]
+        masked: Option<String>,
+        #[structopt(flatten)]
+        payload: PayloadOpt,
+    },
+    /// Read the new messages of a channel
+    Read {
+        #[structopt(flatten)]
+        reader: ReaderOpt,
+        #[structopt(flatten)]
+        payload: PayloadOpt,
+    },
+    /// Keep reading the new messages of a channel
+    Follow {
+        #[structopt(flatten)]
+        reader: ReaderOpt,
+        #[structopt(flatten)]
+        payload: PayloadOpt,
+        /// Seconds between two fetches
+        #[structopt(long, default_value = "10")]
+        interval: u64,
+    },
+    /// Print the encrypted state of a state file, to store it elsewhere
+    ExportState {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// Write the exported state in a file instead of stdout
+        #[structopt(long, parse(from_os_str))]
+        out: Option<PathBuf>,
+    },
+    /// Write an exported state, read from a file or from stdin, in a state file
+    ImportState {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// File with the exported state; `-` reads it from stdin
+        #[structopt(long = "in", default_value = "-")]
+        input: String,
+    },
+    /// Change the password of a state file and of its backups, without connecting to the tangle
+    ChangePassword {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+    },
+    /// Print the information of a state file, without connecting to the tangle
+    InspectState {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// Read only the unencrypted header, without the password
+        #[structopt(long)]
+        header_only: bool,
+    },
+    /// Print the index used to find a message on the tangle
+    MsgIndex {
+        /// Channel address
+        #[structopt(long)]
+        channel: String,
+        #[structopt(long)]
+        msg: String,
+    },
+    /// Verify the chain of a channel, or an audit proof of a message
+    Verify {
+        /// Channel as `<channel_address>:<announcement_id>`
+        #[structopt(long, required_unless = "proof")]
+        channel: Option<String>,
+        /// Hex encoded public key or fingerprint of the expected author; it is required with --proof
+        #[structopt(long)]
+        author: Option<String>,
+        /// File with an audit proof, verified without connecting to the tangle
+        #[structopt(long, parse(from_os_str), requires = "author")]
+        proof: Option<PathBuf>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ReaderOpt {
+    /// Channel as `<channel_address>:<announcement_id>`
+    #[structopt(long)]
+    pub channel: String,
+    /// File where the reader state is kept between runs
+    #[structopt(long, parse(from_os_str))]
+    pub reader_state: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct PayloadOpt {
+    /// Format of the packets: json or raw
+    #[structopt(long, default_value = "json", possible_values = &["json", "raw"])]
+    pub format: String,
+    /// Encrypt the masked part with a key and a nonce created from two secret strings
+    #[structopt(long)]
+    pub encrypted: bool,
+}
+
+fn parse_network(network: &str) -> Result<Network, String> {
+    match network.to_lowercase().as_str() {
+        "mainnet" => Ok(Network::Mainnet),
+        "testnet" => Ok(Network::Testnet),
+        "devnet" => Ok(Network::Devnet),
+        "local" => Ok(Network::Local),
+        _ => Err(format!("Unknown network: {}", network)),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let opt = Opt::from_args();
+    if let Err(e) = commands::run(opt).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
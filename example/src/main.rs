@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 use iota_streams_lib::channels::{ChannelWriter, ChannelReader};
 use iota_streams_lib::payload::payload_serializers::{JsonPacketBuilder, JsonPacket};
 use iota_streams_lib::utility::iota_utility::{create_encryption_key, create_encryption_nonce};
+use iota_streams_lib::user_builders::network::Network;
+use iota_streams_lib::utility::secrets::SymmetricKey;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Message {
@@ -103,6 +105,8 @@ async fn test_restore_channel_from_tangle(channel: &str, announce: &str, key: &[
         channel,
         announce,
         state_psw,
+        &Network::Testnet,
+        None,
         None,
         None).await?;
     println!("... Channel Restored from TANGLE");
@@ -115,26 +119,26 @@ async fn test_restore_channel_from_tangle(channel: &str, announce: &str, key: &[
 }
 
 async fn test_receive_messages(channel_id: &str, announce_id: &str, psw: &str, key: &[u8; 32], nonce: &[u8; 24]) -> Result<Vec<u8>>{
-    let key_nonce = Some((key.clone(), nonce.clone()));
+    let key = SymmetricKey::new(key, nonce);
 
     let mut reader = ChannelReader::builder().build(channel_id, announce_id);
     reader.attach().await?;
     println!("Announce Received");
 
-    print_msgs(&mut reader, key_nonce).await?;
+    print_msgs(&mut reader, Some(&key)).await?;
     reader.export_to_bytes(psw).await
 }
 
 async fn test_restore_reader(state: &[u8], psw: &str, key: &[u8; 32], nonce: &[u8; 24]) -> Result<()>{
-    let key_nonce = Some((key.clone(), nonce.clone()));
+    let key = SymmetricKey::new(key, nonce);
     println!("Restoring reader ...");
     let mut reader = ChannelReader::import_from_bytes(state, psw, None, None).await?;
     println!("... Reader restored");
-    print_msgs(&mut reader, key_nonce).await
+    print_msgs(&mut reader, Some(&key)).await
 }
 
-async fn print_msgs(reader: &mut ChannelReader, key_nonce: Option<([u8;32], [u8;24])>) -> Result<()>{
-    let msgs = reader.fetch_parsed_msgs(&key_nonce).await.unwrap() as Vec<(String, JsonPacket)>;
+async fn print_msgs(reader: &mut ChannelReader, key: Option<&SymmetricKey>) -> Result<()>{
+    let msgs = reader.fetch_parsed_msgs(key).await.unwrap() as Vec<(String, JsonPacket)>;
     println!();
     for (id, packet) in msgs {
         println!("Message Found:");
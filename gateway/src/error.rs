@@ -0,0 +1,52 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+///
+/// Error returned by the gateway endpoints, sent to the client as a JSON object with its HTTP status
+///
+#[derive(Debug)]
+pub struct ApiError{
+    status: StatusCode,
+    msg: String,
+}
+
+impl ApiError{
+    pub fn new(status: StatusCode, msg: &str) -> ApiError{
+        ApiError{
+            status,
+            msg: msg.to_string(),
+        }
+    }
+
+    pub fn bad_request(msg: &str) -> ApiError{
+        ApiError::new(StatusCode::BAD_REQUEST, msg)
+    }
+
+    pub fn unauthorized() -> ApiError{
+        ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid API token")
+    }
+
+    pub fn conflict(msg: &str) -> ApiError{
+        ApiError::new(StatusCode::CONFLICT, msg)
+    }
+
+    pub fn not_found(channel_id: &str) -> ApiError{
+        ApiError::new(StatusCode::NOT_FOUND, &format!("There is no channel {}", channel_id))
+    }
+}
+
+impl From<anyhow::Error> for ApiError{
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError{
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.msg }))).into_response()
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
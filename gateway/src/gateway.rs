@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use iota_streams_lib::channels::channel_state::ChannelState;
+use iota_streams_lib::channels::{ChannelReader, ChannelWriter};
+use iota_streams_lib::payload::payload_serializers::{JsonPacket, JsonPacketBuilder};
+use iota_streams_lib::storage::state_store::{StateNotFoundError, StateStore};
+use iota_streams_lib::transport::channel_transport::{ChannelTransport, StreamsClient};
+use iota_streams_lib::user_builders::node_pool::NodePool;
+use iota_streams_lib::utility::iota_utility::{hash_string, random_seed};
+use iota_streams_lib::utility::secrets::SecretString;
+
+use crate::error::{ApiError, ApiResult};
+
+///
+/// Channels exposed by the gateway.
+/// The writer states are kept in a StateStore, encrypted with the master password;
+/// each channel has its own API token, of which only the hash is stored
+///
+pub struct Gateway<Trans: ChannelTransport = StreamsClient>{
+    states: Arc<dyn StateStore>,
+    tokens: Arc<dyn StateStore>,
+    master_psw: SecretString,
+    transport: Trans,
+    node_pool: Option<NodePool>,
+    writers: Mutex<HashMap<String, Arc<Mutex<ChannelWriter<Trans>>>>>,
+    readers: Mutex<HashMap<String, Arc<Mutex<ChannelReader<Trans>>>>>,
+}
+
+impl Gateway{
+    ///
+    /// Create the gateway on the nodes of the pool. The channels switch node when the current one stops working
+    ///
+    pub fn new(states: Arc<dyn StateStore>, tokens: Arc<dyn StateStore>, master_psw: impl Into<SecretString>, node_pool: NodePool) -> Gateway{
+        let mut gateway = Gateway::with_transport(states, tokens, master_psw, node_pool.client());
+        gateway.node_pool = Some(node_pool);
+        gateway
+    }
+}
+
+impl<Trans: ChannelTransport> Gateway<Trans>{
+    ///
+    /// Create the gateway on the specified transport, for example a MemoryTransport, so the channels don't use any node
+    ///
+    pub fn with_transport(states: Arc<dyn StateStore>, tokens: Arc<dyn StateStore>, master_psw: impl Into<SecretString>, transport: Trans) -> Gateway<Trans>{
+        Gateway{
+            states,
+            tokens,
+            master_psw: master_psw.into(),
+            transport,
+            node_pool: None,
+            writers: Mutex::new(HashMap::new()),
+            readers: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<Trans: ChannelTransport> Gateway<Trans>{
+    ///
+    /// Create and announce a new channel. It returns the channel address, the announcement id and the API token
+    ///
+    pub async fn create_channel(&self, seed: Option<&str>) -> ApiResult<(String, String, String)>{
+        let mut builder = ChannelWriter::builder();
+        if let Some(seed) = seed{
+            builder = builder.seed(seed);
+        }
+        let mut writer = builder.build_with_transport(self.transport.clone());
+        if let Some(node_pool) = &self.node_pool{
+            writer.set_node_pool(node_pool.clone());
+        }
+        let (channel_id, announce_id) = writer.open().await?;
+        let token = self.register(writer, None).await?;
+        Ok((channel_id, announce_id, token))
+    }
+
+    ///
+    /// Restore a channel from a state exported with `export_state`, replacing its API token.
+    /// A channel already in the gateway can be imported again only with its current API token
+    ///
+    pub async fn import_state(&self, state: &str, psw: &str, token: Option<&str>) -> ApiResult<(String, String, String)>{
+        let writer = match self.restore_writer(state.trim().as_bytes(), psw).await{
+            Ok(writer) => writer,
+            Err(e) => return Err(ApiError::bad_request(&format!("Invalid state: {}", e)))
+        };
+        let (channel_id, announce_id) = writer.channel_address();
+        let token = self.register(writer, token).await?;
+        Ok((channel_id, announce_id, token))
+    }
+
+    ///
+    /// Export the state of a channel, encrypted with the specified password
+    ///
+    pub async fn export_state(&self, channel_id: &str, psw: &str) -> ApiResult<String>{
+        let writer = self.writer(channel_id).await?;
+        let writer = writer.lock().await;
+        let state = writer.export_to_bytes(psw).await?;
+        Ok(String::from_utf8_lossy(&state).to_string())
+    }
+
+    ///
+    /// Send a signed packet with JSON data. It returns the message id, its index and the error of the state save, if any.
+    /// Once the packet is sent the request doesn't fail, otherwise the client would send it again:
+    /// the state is saved again with the next packet, and a writer loaded from an old state is updated from the tangle
+    ///
+    pub async fn publish(&self, channel_id: &str, public: &Value, masked: &Value) -> ApiResult<(String, String, Option<String>)>{
+        let mut builder = JsonPacketBuilder::new();
+        if let Err(e) = builder.public(public).and_then(|b| b.masked(masked)){
+            return Err(ApiError::bad_request(&e.to_string()));
+        }
+
+        let writer = self.writer(channel_id).await?;
+        let mut writer = writer.lock().await;
+        let msg_id = writer.send_signed_packet(&builder.build()).await?;
+        let index = writer.msg_index(&msg_id)?;
+        let state_error = writer.persist_to(self.states.as_ref(), self.master_psw.expose()).await.err();
+        Ok((msg_id, index, state_error.map(|e| e.to_string())))
+    }
+
+    ///
+    /// Get a page of the messages of a channel, from the oldest one. It returns the page and the total number of messages.
+    /// The reader keeps the messages already received, so only the new ones are fetched
+    ///
+    pub async fn messages(&self, channel_id: &str, offset: usize, limit: usize) -> ApiResult<(Vec<Value>, usize)>{
+        let reader = self.reader(channel_id).await?;
+        let mut reader = reader.lock().await;
+        let (msgs, total) = reader.replay_page(offset, limit).await?;
+
+        let page = msgs.iter()
+            .map(|(msg_id, p, m)| msg_to_json(msg_id, p, m))
+            .collect();
+        Ok((page, total))
+    }
+
+    ///
+    /// Get the index used to find a message of a channel on the tangle
+    ///
+    pub async fn msg_index(&self, channel_id: &str, msg_id: &str) -> ApiResult<String>{
+        let writer = self.writer(channel_id).await?;
+        let writer = writer.lock().await;
+        match writer.msg_index(msg_id){
+            Ok(index) => Ok(index),
+            Err(e) => Err(ApiError::bad_request(&e.to_string()))
+        }
+    }
+
+    ///
+    /// Check that the token is the API token of the channel
+    ///
+    pub fn check_token(&self, channel_id: &str, token: Option<&str>) -> ApiResult<()>{
+        let token = match token{
+            Some(token) => token,
+            None => return Err(ApiError::unauthorized())
+        };
+        let hash = match self.tokens.load(channel_id){
+            Ok(hash) => hash,
+            Err(e) if StateNotFoundError::is_not_found(&e) => return Err(ApiError::not_found(channel_id)),
+            Err(e) => return Err(e.into())
+        };
+        match token_matches(&hash, token){
+            true => Ok(()),
+            false => Err(ApiError::unauthorized())
+        }
+    }
+}
+
+impl<Trans: ChannelTransport> Gateway<Trans>{
+    async fn register(&self, writer: ChannelWriter<Trans>, token: Option<&str>) -> ApiResult<String>{
+        let (channel_id, _) = writer.channel_address();
+        // Otherwise anyone knowing the address of a channel could take it over, replacing its token
+        match self.tokens.load(&channel_id){
+            Ok(hash) if token.map_or(false, |token| token_matches(&hash, token)) => {},
+            Ok(_) => return Err(ApiError::conflict(&format!("The channel {} already exists: its API token is needed to replace it", channel_id))),
+            Err(e) if StateNotFoundError::is_not_found(&e) => {},
+            Err(e) => return Err(e.into())
+        }
+        writer.persist_to(self.states.as_ref(), self.master_psw.expose()).await?;
+
+        let token = random_seed();
+        self.tokens.save(&channel_id, hash_string(&token).as_bytes())?;
+
+        self.writers.lock().await.insert(channel_id.clone(), Arc::new(Mutex::new(writer)));
+        self.readers.lock().await.remove(&channel_id);
+        Ok(token)
+    }
+
+    async fn writer(&self, channel_id: &str) -> ApiResult<Arc<Mutex<ChannelWriter<Trans>>>>{
+        if let Some(writer) = self.writers.lock().await.get(channel_id){
+            return Ok(writer.clone());
+        }
+
+        // The channel is loaded without locking the map, so the requests of the other channels are not blocked meanwhile
+        let state = match self.states.load(channel_id){
+            Ok(state) => state,
+            Err(e) if StateNotFoundError::is_not_found(&e) => return Err(ApiError::not_found(channel_id)),
+            Err(e) => return Err(e.into())
+        };
+        let writer = self.restore_writer(&state, self.master_psw.expose()).await?;
+
+        // If the channel has been loaded by another request in the meantime, that writer is kept
+        let mut writers = self.writers.lock().await;
+        let writer = writers.entry(channel_id.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(writer)));
+        Ok(writer.clone())
+    }
+
+    async fn reader(&self, channel_id: &str) -> ApiResult<Arc<Mutex<ChannelReader<Trans>>>>{
+        if let Some(reader) = self.readers.lock().await.get(channel_id){
+            return Ok(reader.clone());
+        }
+
+        let (channel_id, announce_id) = self.writer(channel_id).await?.lock().await.channel_address();
+        let mut reader = ChannelReader::builder()
+            .build_with_transport(&channel_id, &announce_id, self.transport.clone());
+        if let Some(node_pool) = &self.node_pool{
+            reader.set_node_pool(node_pool.clone());
+        }
+        reader.attach().await?;
+
+        let mut readers = self.readers.lock().await;
+        let reader = readers.entry(channel_id)
+            .or_insert_with(|| Arc::new(Mutex::new(reader)));
+        Ok(reader.clone())
+    }
+
+    ///
+    /// Restore a writer from an encrypted state, on the transport and the nodes of the gateway
+    ///
+    async fn restore_writer(&self, state: &[u8], psw: &str) -> Result<ChannelWriter<Trans>>{
+        let channel_state = ChannelState::decrypt(state, psw)?;
+        let mut writer = ChannelWriter::import_with_transport(&channel_state, psw, self.transport.clone()).await?;
+        if let Some(node_pool) = &self.node_pool{
+            writer.set_node_pool(node_pool.clone());
+        }
+        Ok(writer)
+    }
+}
+
+fn msg_to_json(msg_id: &str, p: &[u8], m: &[u8]) -> Value{
+    match JsonPacket::from_streams_response(p, m, None){
+        Ok(packet) => json!({
+            "msg_id": msg_id,
+            "public": packet.deserialize_public::<Value>().ok(),
+            "masked": packet.deserialize_masked::<Value>().ok(),
+        }),
+        Err(_) => json!({ "msg_id": msg_id, "public": null, "masked": null }),
+    }
+}
+
+fn token_matches(hash: &[u8], token: &str) -> bool{
+    constant_time_eq(hash, hash_string(token).as_bytes())
+}
+
+///
+/// Compare the bytes in a time that depends only on their length, so the hash of a token can't be guessed from the response times
+///
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool{
+    if a.len() != b.len(){
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use iota_streams_lib::storage::memory_state_store::MemoryStateStore;
+    use iota_streams_lib::transport::memory_transport::MemoryTransport;
+
+    ///
+    /// Store that can be made to fail the saves
+    ///
+    #[derive(Default)]
+    struct FlakyStore{
+        store: MemoryStateStore,
+        failing: AtomicBool,
+    }
+
+    impl StateStore for FlakyStore{
+        fn save(&self, channel_id: &str, state: &[u8]) -> Result<()> {
+            match self.failing.load(Ordering::SeqCst){
+                true => Err(anyhow::Error::msg("The store is not writable")),
+                false => self.store.save(channel_id, state)
+            }
+        }
+
+        fn load(&self, channel_id: &str) -> Result<Vec<u8>> {
+            self.store.load(channel_id)
+        }
+
+        fn list(&self) -> Result<Vec<String>> {
+            self.store.list()
+        }
+
+        fn delete(&self, channel_id: &str) -> Result<()> {
+            self.store.delete(channel_id)
+        }
+    }
+
+    fn gateway(states: Arc<dyn StateStore>, tokens: Arc<dyn StateStore>, transport: &MemoryTransport) -> Gateway<MemoryTransport>{
+        Gateway::with_transport(states, tokens, "masterpsw", transport.clone())
+    }
+
+    fn status(err: ApiError) -> StatusCode{
+        err.into_response().status()
+    }
+
+    #[tokio::test]
+    async fn messages_are_paged() {
+        let transport = MemoryTransport::new();
+        let gateway = gateway(Arc::new(MemoryStateStore::new()), Arc::new(MemoryStateStore::new()), &transport);
+        let (channel_id, _, _) = gateway.create_channel(None).await.unwrap();
+        for n in 0..3{
+            gateway.publish(&channel_id, &json!({ "n": n }), &Value::Null).await.unwrap();
+        }
+
+        let (page, total) = gateway.messages(&channel_id, 0, 2).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0]["public"], json!({ "n": 0 }));
+
+        gateway.publish(&channel_id, &json!({ "n": 3 }), &Value::Null).await.unwrap();
+        let (page, total) = gateway.messages(&channel_id, 2, 2).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(page.iter().map(|msg| msg["public"]["n"].clone()).collect::<Vec<Value>>(), vec![json!(2), json!(3)]);
+    }
+
+    #[tokio::test]
+    async fn tokens_are_checked() {
+        let transport = MemoryTransport::new();
+        let gateway = gateway(Arc::new(MemoryStateStore::new()), Arc::new(MemoryStateStore::new()), &transport);
+        let (channel_id, _, token) = gateway.create_channel(None).await.unwrap();
+
+        assert!(gateway.check_token(&channel_id, Some(&token)).is_ok());
+        assert_eq!(status(gateway.check_token(&channel_id, Some("wrong")).unwrap_err()), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(gateway.check_token(&channel_id, None).unwrap_err()), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(gateway.check_token("unknown", Some(&token)).unwrap_err()), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn existing_channel_is_imported_only_with_its_token() {
+        let transport = MemoryTransport::new();
+        let gateway = gateway(Arc::new(MemoryStateStore::new()), Arc::new(MemoryStateStore::new()), &transport);
+        let (channel_id, _, token) = gateway.create_channel(None).await.unwrap();
+        let state = gateway.export_state(&channel_id, "psw").await.unwrap();
+
+        assert_eq!(status(gateway.import_state(&state, "psw", None).await.unwrap_err()), StatusCode::CONFLICT);
+        assert_eq!(status(gateway.import_state(&state, "psw", Some("wrong")).await.unwrap_err()), StatusCode::CONFLICT);
+        gateway.check_token(&channel_id, Some(&token)).unwrap();
+
+        let (_, _, new_token) = gateway.import_state(&state, "psw", Some(&token)).await.unwrap();
+        gateway.check_token(&channel_id, Some(&new_token)).unwrap();
+        assert_eq!(status(gateway.check_token(&channel_id, Some(&token)).unwrap_err()), StatusCode::UNAUTHORIZED);
+
+        let other = Gateway::with_transport(Arc::new(MemoryStateStore::new()), Arc::new(MemoryStateStore::new()), "masterpsw", transport.clone());
+        let (imported_id, _, _) = other.import_state(&state, "psw", None).await.unwrap();
+        assert_eq!(imported_id, channel_id);
+    }
+
+    #[tokio::test]
+    async fn sent_packet_is_not_failed_by_the_state_save() {
+        let transport = MemoryTransport::new();
+        let states = Arc::new(FlakyStore::default());
+        let gateway = gateway(states.clone(), Arc::new(MemoryStateStore::new()), &transport);
+        let (channel_id, _, _) = gateway.create_channel(None).await.unwrap();
+
+        states.failing.store(true, Ordering::SeqCst);
+        let (msg_id, _, state_error) = gateway.publish(&channel_id, &json!({ "n": 0 }), &Value::Null).await.unwrap();
+        assert!(state_error.is_some());
+
+        states.failing.store(false, Ordering::SeqCst);
+        let (_, _, state_error) = gateway.publish(&channel_id, &json!({ "n": 1 }), &Value::Null).await.unwrap();
+        assert!(state_error.is_none());
+        let (page, _) = gateway.messages(&channel_id, 0, 10).await.unwrap();
+        assert_eq!(page[0]["msg_id"], json!(msg_id));
+        assert_eq!(page.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn stored_channels_are_loaded() {
+        let transport = MemoryTransport::new();
+        let states: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let tokens: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let (channel_id, _, token) = gateway(states.clone(), tokens.clone(), &transport).create_channel(None).await.unwrap();
+
+        let restarted = gateway(states, tokens, &transport);
+        restarted.check_token(&channel_id, Some(&token)).unwrap();
+        restarted.publish(&channel_id, &json!({ "n": 0 }), &Value::Null).await.unwrap();
+        let (_, total) = restarted.messages(&channel_id, 0, 10).await.unwrap();
+        assert_eq!(total, 1);
+    }
+}
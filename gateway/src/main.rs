@@ -0,0 +1,118 @@
+mod error;
+mod gateway;
+mod routes;
+
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use iota_streams_lib::storage::file_state_store::FileStateStore;
+use iota_streams_lib::storage::memory_state_store::MemoryStateStore;
+use iota_streams_lib::storage::state_store::StateStore;
+use iota_streams_lib::transport::memory_transport::MemoryTransport;
+use iota_streams_lib::user_builders::network::Network;
+use iota_streams_lib::user_builders::node_pool::NodePool;
+use iota_streams_lib::utility::secrets::SecretString;
+
+use crate::gateway::Gateway;
+use crate::routes::GatewayTransport;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "streams-gateway",
+    about = "Expose IOTA Streams channels over HTTP",
+    after_help = "The password used to encrypt the stored channel states is read from STREAMS_GATEWAY_PSW; if it is not set it is asked on the terminal"
+)]
+pub struct Opt {
+    /// Address where the gateway listens
+    #[structopt(long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+
+    /// Directory where the channel states and the API tokens are stored
+    #[structopt(long, required_unless = "memory")]
+    store_dir: Option<String>,
+
+    /// Keep the channel states, the API tokens and the messages in memory, without any node, for testing
+    #[structopt(long)]
+    memory: bool,
+
+    /// Url of the node; it replaces the default nodes of the network
+    #[structopt(long)]
+    node: Option<String>,
+
+    /// Network of the node: mainnet, testnet, devnet or local
+    #[structopt(long, default_value = "testnet", parse(try_from_str = parse_network))]
+    network: Network,
+
+    /// Use the private tangle of a node running on the local machine, for testing
+    #[structopt(long)]
+    local: bool,
+}
+
+const PSW_ENV: &str = "STREAMS_GATEWAY_PSW";
+
+fn parse_network(network: &str) -> Result<Network, String> {
+    match network.to_lowercase().as_str() {
+        "mainnet" => Ok(Network::Mainnet),
+        "testnet" => Ok(Network::Testnet),
+        "devnet" => Ok(Network::Devnet),
+        "local" => Ok(Network::Local),
+        _ => Err(format!("Unknown network: {}", network)),
+    }
+}
+
+fn stores(opt: &Opt) -> Result<(Arc<dyn StateStore>, Arc<dyn StateStore>)> {
+    match &opt.store_dir {
+        Some(dir) if !opt.memory => Ok((
+            Arc::new(FileStateStore::new(&format!("{}/states", dir))?),
+            Arc::new(FileStateStore::new(&format!("{}/tokens", dir))?),
+        )),
+        _ => Ok((Arc::new(MemoryStateStore::new()), Arc::new(MemoryStateStore::new()))),
+    }
+}
+
+///
+/// Read the master password from the environment or, if it is not set, from the terminal.
+/// It is never taken as an argument, since it would be visible in the process list and in the shell history
+///
+fn master_psw() -> Result<SecretString> {
+    match env::var(PSW_ENV) {
+        Ok(psw) => Ok(SecretString::from(psw)),
+        Err(_) => Ok(SecretString::from(rpassword::read_password_from_tty(Some("Master password: "))?)),
+    }
+}
+
+async fn run(opt: Opt) -> Result<()> {
+    let (states, tokens) = stores(&opt)?;
+    let psw = master_psw()?;
+    if opt.memory {
+        return serve(Gateway::with_transport(states, tokens, psw, MemoryTransport::new()), opt.bind).await;
+    }
+
+    let network = match opt.local {
+        true => Network::Local,
+        false => opt.network.clone(),
+    };
+    let node_pool = NodePool::from_network(&network).override_with(opt.node.as_deref(), None);
+    serve(Gateway::new(states, tokens, psw, node_pool), opt.bind).await
+}
+
+async fn serve<Trans: GatewayTransport>(gateway: Gateway<Trans>, bind: SocketAddr) -> Result<()> {
+    println!("Listening on {}", bind);
+    axum::Server::bind(&bind)
+        .serve(routes::router(Arc::new(gateway)).into_make_service())
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let opt = Opt::from_args();
+    if let Err(e) = run(opt).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
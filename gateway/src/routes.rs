@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::HeaderMap;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use iota_streams_lib::transport::channel_transport::ChannelTransport;
+
+use crate::error::{ApiError, ApiResult};
+use crate::gateway::Gateway;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Deserialize, Default)]
+pub struct CreateRequest{
+    seed: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PublishRequest{
+    public: Value,
+    #[serde(default)]
+    masked: Value,
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery{
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ExportRequest{
+    psw: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImportRequest{
+    state: String,
+    psw: String,
+}
+
+///
+/// Transport that can be shared by the handlers of the gateway
+///
+pub trait GatewayTransport: ChannelTransport + Send + Sync + 'static{}
+
+impl<Trans: ChannelTransport + Send + Sync + 'static> GatewayTransport for Trans{}
+
+///
+/// Routes of the gateway. All the routes of an existing channel require its API token as `Authorization: Bearer <token>`
+///
+pub fn router<Trans: GatewayTransport>(gateway: Arc<Gateway<Trans>>) -> Router{
+    Router::new()
+        .route("/channels", post(create_channel::<Trans>))
+        .route("/channels/import", post(import_state::<Trans>))
+        .route("/channels/:channel_id/messages", post(publish::<Trans>).get(messages::<Trans>))
+        .route("/channels/:channel_id/messages/:msg_id/index", get(msg_index::<Trans>))
+        .route("/channels/:channel_id/state", post(export_state::<Trans>))
+        .layer(Extension(gateway))
+}
+
+async fn create_channel<Trans: GatewayTransport>(Extension(gateway): Extension<Arc<Gateway<Trans>>>, body: Option<Json<CreateRequest>>) -> ApiResult<Json<Value>>{
+    let Json(req) = body.unwrap_or_default();
+    let (channel_id, announce_id, token) = gateway.create_channel(req.seed.as_deref()).await?;
+    Ok(Json(json!({ "channel": channel_id, "announcement": announce_id, "token": token })))
+}
+
+///
+/// A channel already in the gateway is replaced only if the request has its API token
+///
+async fn import_state<Trans: GatewayTransport>(
+    Extension(gateway): Extension<Arc<Gateway<Trans>>>,
+    headers: HeaderMap,
+    Json(req): Json<ImportRequest>
+) -> ApiResult<Json<Value>>{
+    let (channel_id, announce_id, token) = gateway.import_state(&req.state, &req.psw, bearer_token(&headers)).await?;
+    Ok(Json(json!({ "channel": channel_id, "announcement": announce_id, "token": token })))
+}
+
+async fn publish<Trans: GatewayTransport>(
+    Extension(gateway): Extension<Arc<Gateway<Trans>>>,
+    Path(channel_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<PublishRequest>
+) -> ApiResult<Json<Value>>{
+    gateway.check_token(&channel_id, bearer_token(&headers))?;
+    let (msg_id, index, state_error) = gateway.publish(&channel_id, &req.public, &req.masked).await?;
+    Ok(Json(json!({ "msg_id": msg_id, "msg_index": index, "state_saved": state_error.is_none(), "state_error": state_error })))
+}
+
+async fn messages<Trans: GatewayTransport>(
+    Extension(gateway): Extension<Arc<Gateway<Trans>>>,
+    Path(channel_id): Path<String>,
+    headers: HeaderMap,
+    Query(page): Query<PageQuery>
+) -> ApiResult<Json<Value>>{
+    gateway.check_token(&channel_id, bearer_token(&headers))?;
+    let offset = page.offset.unwrap_or(0);
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE{
+        return Err(ApiError::bad_request(&format!("The limit must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+
+    let (msgs, total) = gateway.messages(&channel_id, offset, limit).await?;
+    let next_offset = match offset + msgs.len() < total{
+        true => Some(offset + msgs.len()),
+        false => None
+    };
+    Ok(Json(json!({ "messages": msgs, "total": total, "next_offset": next_offset })))
+}
+
+async fn msg_index<Trans: GatewayTransport>(
+    Extension(gateway): Extension<Arc<Gateway<Trans>>>,
+    Path((channel_id, msg_id)): Path<(String, String)>,
+    headers: HeaderMap
+) -> ApiResult<Json<Value>>{
+    gateway.check_token(&channel_id, bearer_token(&headers))?;
+    let index = gateway.msg_index(&channel_id, &msg_id).await?;
+    Ok(Json(json!({ "msg_id": msg_id, "msg_index": index })))
+}
+
+async fn export_state<Trans: GatewayTransport>(
+    Extension(gateway): Extension<Arc<Gateway<Trans>>>,
+    Path(channel_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<ExportRequest>
+) -> ApiResult<Json<Value>>{
+    gateway.check_token(&channel_id, bearer_token(&headers))?;
+    let state = gateway.export_state(&channel_id, &req.psw).await?;
+    Ok(Json(json!({ "channel": channel_id, "state": state })))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str>{
+    headers.get("authorization")?
+        .to_str().ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim())
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::channels::channel_state::ChannelState;
+use crate::storage::state_store::StateStore;
+use crate::utility::secrets::SecretString;
+
+const MIN_AUTOSAVE_WAIT: Duration = Duration::from_millis(100);
+
+///
+/// Where the channel state is saved by the autosave
+///
+#[derive(Clone)]
+pub enum AutosaveTarget{
+    File(String),
+    Store(Arc<dyn StateStore>),
+}
+
+///
+/// Policy used to save the encrypted channel state after each message successfully sent.
+/// With a debounce interval, the state is saved at most once per interval: the skipped saves
+/// are done with the next message, with `ChannelWriter::flush_autosave` or, when the interval expires,
+/// by `ChannelWriter::flush_autosave_periodically`.
+///
+#[derive(Clone)]
+pub struct AutosavePolicy{
+    target: AutosaveTarget,
+    psw: SecretString,
+    debounce: Duration,
+}
+
+impl AutosavePolicy{
+    ///
+    /// Save the state in a file, encrypted with the specified password
+    ///
+    pub fn to_file(file_path: &str, psw: impl Into<SecretString>) -> AutosavePolicy{
+        let psw = psw.into();
+        AutosavePolicy{
+            target: AutosaveTarget::File(file_path.to_string()),
+            psw,
+            debounce: Duration::from_secs(0),
+        }
+    }
+
+    ///
+    /// Save the state in a StateStore, encrypted with the specified password
+    ///
+    pub fn to_store(store: Arc<dyn StateStore>, psw: impl Into<SecretString>) -> AutosavePolicy{
+        let psw = psw.into();
+        AutosavePolicy{
+            target: AutosaveTarget::Store(store),
+            psw,
+            debounce: Duration::from_secs(0),
+        }
+    }
+
+    pub fn debounce(mut self, debounce: Duration) -> Self{
+        self.debounce = debounce;
+        self
+    }
+}
+
+impl AutosavePolicy{
+    pub(crate) fn psw(&self) -> &str{
+        self.psw.expose()
+    }
+
+    pub(crate) fn is_due(&self, last_save: Option<Instant>) -> bool{
+        match last_save{
+            None => true,
+            Some(last) => last.elapsed() >= self.debounce
+        }
+    }
+
+    ///
+    /// Time left before the debounce interval expires, at least the minimum wait of the periodic save
+    ///
+    pub(crate) fn remaining(&self, last_save: Option<Instant>) -> Duration{
+        let remaining = match last_save{
+            None => Duration::from_secs(0),
+            Some(last) => self.debounce.saturating_sub(last.elapsed())
+        };
+        remaining.max(MIN_AUTOSAVE_WAIT)
+    }
+
+    pub(crate) fn save(&self, channel_state: &ChannelState) -> Result<()>{
+        match &self.target{
+            AutosaveTarget::File(file_path) => channel_state.write_to_file(file_path, self.psw()),
+            AutosaveTarget::Store(store) => store.save(&channel_state.channel_id(), &channel_state.encrypt(self.psw())?)
+        }
+    }
+}
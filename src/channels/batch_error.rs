@@ -0,0 +1,105 @@
+use std::fmt::{Display, Formatter};
+use crate::channels::UnreachableNodeError;
+
+///
+/// Error returned by `ChannelWriter::send_batch` when one of the packets cannot be published.
+/// The packets before `resume_index` have been attached to the channel and the ones after it have not.
+/// The packet at `resume_index` has not been attached, unless `attach_unknown` is true:
+/// the node became unreachable while sending it, so it may have been attached.
+///
+#[derive(Debug)]
+pub struct BatchSendError{
+    published: Vec<String>,
+    attach_unknown: bool,
+    cause: anyhow::Error,
+}
+
+impl BatchSendError{
+    pub fn new(published: Vec<String>, cause: anyhow::Error) -> BatchSendError{
+        BatchSendError{
+            published,
+            attach_unknown: UnreachableNodeError::is_unreachable(&cause),
+            cause
+        }
+    }
+
+    ///
+    /// Error of a batch stopped before sending its first packet
+    ///
+    pub(crate) fn not_started(cause: anyhow::Error) -> BatchSendError{
+        BatchSendError{
+            published: vec![],
+            attach_unknown: false,
+            cause
+        }
+    }
+
+    ///
+    /// Msg ids of the packets already attached, in the same order of the batch
+    ///
+    pub fn published(&self) -> &[String] {
+        &self.published
+    }
+
+    ///
+    /// Index of the first packet of the batch that has not been published
+    ///
+    pub fn resume_index(&self) -> usize {
+        self.published.len()
+    }
+
+    ///
+    /// Check if it is unknown whether the packet at `resume_index` has been attached, since the node is not reachable.
+    /// Import the channel again from the tangle, that walks it to the last attached message, before sending it again, otherwise it may be attached twice
+    ///
+    pub fn attach_unknown(&self) -> bool {
+        self.attach_unknown
+    }
+
+    ///
+    /// The error that stopped the batch
+    ///
+    pub fn cause(&self) -> &anyhow::Error {
+        &self.cause
+    }
+}
+
+impl Display for BatchSendError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.attach_unknown{
+            write!(f, "Batch interrupted after {} published packets, the next one may have been attached: {}", self.published.len(), self.cause)
+        } else {
+            write!(f, "Batch interrupted after {} published packets: {}", self.published.len(), self.cause)
+        }
+    }
+}
+
+impl std::error::Error for BatchSendError{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.cause.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_index_follows_the_published_packets() {
+        let published = vec!["first".to_string(), "second".to_string()];
+        let err = BatchSendError::new(published.clone(), anyhow::Error::msg("node error"));
+        assert_eq!(err.resume_index(), 2);
+        assert_eq!(err.published(), published.as_slice());
+        assert_eq!(err.cause().to_string(), "node error");
+        assert!(!err.attach_unknown());
+    }
+
+    #[test]
+    fn unreachable_node_leaves_the_next_packet_unknown() {
+        let cause = UnreachableNodeError::new(anyhow::Error::msg("timeout"));
+        let err = BatchSendError::new(vec!["first".to_string()], cause.into());
+        assert_eq!(err.resume_index(), 1);
+        assert!(err.attach_unknown());
+        assert!(!BatchSendError::not_started(anyhow::Error::msg("timeout")).attach_unknown());
+    }
+}
@@ -1,18 +1,32 @@
 use crate::user_builders::author_builder::AuthorBuilder;
 use iota_streams::app::transport::tangle::client::SendOptions;
-use crate::channels::{ChannelReader, ChannelWriter};
+use crate::channels::{AutosavePolicy, ChannelReader, ChannelWriter, SnapshotPolicy};
 use crate::user_builders::subscriber_builder::SubscriberBuilder;
+use crate::utility::retry_policy::RetryPolicy;
+use crate::utility::secrets::SecretString;
+use crate::user_builders::node_pool::FailoverStrategy;
+use crate::user_builders::network::Network;
+use iota_streams::app_channels::api::ChannelType;
+use crate::transport::channel_transport::ChannelTransport;
 
 
 pub struct ChannelWriterBuilder{
-    author_builder: AuthorBuilder
+    author_builder: AuthorBuilder,
+    outbox: Option<(String, SecretString)>,
+    retry_policy: RetryPolicy,
+    snapshot_policy: Option<SnapshotPolicy>,
+    autosave_policy: Option<AutosavePolicy>,
 }
 
 impl ChannelWriterBuilder{
 
     pub fn new() -> ChannelWriterBuilder{
         ChannelWriterBuilder{
-            author_builder: AuthorBuilder::new()
+            author_builder: AuthorBuilder::new(),
+            outbox: None,
+            retry_policy: RetryPolicy::default(),
+            snapshot_policy: None,
+            autosave_policy: None,
         }
     }
 
@@ -21,31 +35,109 @@ impl ChannelWriterBuilder{
         self
     }
 
+    pub fn mnemonic(mut self, mnemonic: &str, passphrase: Option<&str>) -> anyhow::Result<Self>{
+        self.author_builder = self.author_builder.mnemonic(mnemonic, passphrase)?;
+        Ok(self)
+    }
+
+    pub fn channel_type(mut self, channel_type: ChannelType) -> Self{
+        self.author_builder = self.author_builder.channel_type(channel_type);
+        self
+    }
+
     pub fn node(mut self, node_url: &str) -> Self{
         self.author_builder = self.author_builder.node(node_url);
         self
     }
 
+    pub fn nodes(mut self, node_urls: &[&str]) -> Self{
+        self.author_builder = self.author_builder.nodes(node_urls);
+        self
+    }
+
+    pub fn failover_strategy(mut self, strategy: FailoverStrategy) -> Self{
+        self.author_builder = self.author_builder.failover_strategy(strategy);
+        self
+    }
+
+    pub fn network(mut self, network: Network) -> Self{
+        self.author_builder = self.author_builder.network(network);
+        self
+    }
+
     pub fn send_options(mut self, send_options: SendOptions) -> Self{
         self.author_builder = self.author_builder.send_options(send_options);
         self
     }
 
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self{
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn snapshot_policy(mut self, snapshot_policy: SnapshotPolicy) -> Self{
+        self.snapshot_policy = Some(snapshot_policy);
+        self
+    }
+
+    ///
+    /// Opt in to save the encrypted channel state after each message successfully sent
+    ///
+    pub fn autosave(mut self, autosave_policy: AutosavePolicy) -> Self{
+        self.autosave_policy = Some(autosave_policy);
+        self
+    }
+
+    pub fn outbox(mut self, file_path: &str, psw: impl Into<SecretString>) -> Self{
+        self.outbox = Some((file_path.to_string(), psw.into()));
+        self
+    }
+
     pub fn build(self) -> ChannelWriter{
-        ChannelWriter::new(self.author_builder.build())
+        let node_pool = self.author_builder.node_pool();
+        let client = node_pool.client();
+        let mut writer = self.build_with_transport(client);
+        writer.set_node_pool(node_pool);
+        writer
+    }
+
+    ///
+    /// Build the channel on the specified transport, for example a MemoryTransport.
+    /// The nodes are ignored, so the channel never switches node
+    ///
+    pub fn build_with_transport<Trans: ChannelTransport>(self, transport: Trans) -> ChannelWriter<Trans>{
+        let channel_type = self.author_builder.channel_type_name();
+        let mut writer = ChannelWriter::new(self.author_builder.build_with_transport(transport.clone()));
+        writer.set_transport(transport);
+        writer.set_channel_type(channel_type);
+        writer.set_retry_policy(self.retry_policy);
+        if let Some(snapshot_policy) = self.snapshot_policy{
+            writer.set_snapshot_policy(snapshot_policy);
+        }
+        if let Some(autosave_policy) = self.autosave_policy{
+            writer.set_autosave_policy(autosave_policy);
+        }
+        if let Some((file_path, psw)) = self.outbox{
+            writer.enable_outbox(&file_path, psw);
+        }
+        writer
     }
 }
 
 
 pub struct ChannelReaderBuilder{
-    subscriber_builder: SubscriberBuilder
+    subscriber_builder: SubscriberBuilder,
+    retry_policy: RetryPolicy,
+    pinned_author: Option<String>,
 }
 
 impl ChannelReaderBuilder{
 
     pub fn new() -> ChannelReaderBuilder{
         ChannelReaderBuilder{
-            subscriber_builder: SubscriberBuilder::new()
+            subscriber_builder: SubscriberBuilder::new(),
+            retry_policy: RetryPolicy::default(),
+            pinned_author: None,
         }
     }
 
@@ -54,17 +146,69 @@ impl ChannelReaderBuilder{
         self
     }
 
+    pub fn mnemonic(mut self, mnemonic: &str, passphrase: Option<&str>) -> anyhow::Result<Self>{
+        self.subscriber_builder = self.subscriber_builder.mnemonic(mnemonic, passphrase)?;
+        Ok(self)
+    }
+
     pub fn node(mut self, node_url: &str) -> Self{
         self.subscriber_builder = self.subscriber_builder.node(node_url);
         self
     }
 
+    pub fn nodes(mut self, node_urls: &[&str]) -> Self{
+        self.subscriber_builder = self.subscriber_builder.nodes(node_urls);
+        self
+    }
+
+    pub fn failover_strategy(mut self, strategy: FailoverStrategy) -> Self{
+        self.subscriber_builder = self.subscriber_builder.failover_strategy(strategy);
+        self
+    }
+
+    pub fn network(mut self, network: Network) -> Self{
+        self.subscriber_builder = self.subscriber_builder.network(network);
+        self
+    }
+
     pub fn send_options(mut self, send_options: SendOptions) -> Self{
         self.subscriber_builder = self.subscriber_builder.send_options(send_options);
         self
     }
 
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self{
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    ///
+    /// Accept only the announcement and the packets signed by the author with the specified
+    /// hex encoded public key or fingerprint
+    ///
+    pub fn pin_author(mut self, public_key: &str) -> Self{
+        self.pinned_author = Some(public_key.to_string());
+        self
+    }
+
     pub fn build(self, channel_id: &str, announce_id: &str) -> ChannelReader{
-        ChannelReader::new(self.subscriber_builder.build(), channel_id, announce_id)
+        let node_pool = self.subscriber_builder.node_pool();
+        let client = node_pool.client();
+        let mut reader = self.build_with_transport(channel_id, announce_id, client);
+        reader.set_node_pool(node_pool);
+        reader
+    }
+
+    ///
+    /// Build the reader on the specified transport, for example a MemoryTransport.
+    /// The nodes are ignored, so the reader never switches node
+    ///
+    pub fn build_with_transport<Trans: ChannelTransport>(self, channel_id: &str, announce_id: &str, transport: Trans) -> ChannelReader<Trans>{
+        let mut reader = ChannelReader::new(self.subscriber_builder.build_with_transport(transport.clone()), channel_id, announce_id);
+        reader.set_transport(transport);
+        reader.set_retry_policy(self.retry_policy);
+        if let Some(public_key) = self.pinned_author{
+            reader.pin_author(&public_key);
+        }
+        reader
     }
 }
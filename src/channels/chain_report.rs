@@ -0,0 +1,90 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+///
+/// Problem found while verifying the chain of a channel
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChainIssue{
+    ///
+    /// The announcement is not signed by the pinned author
+    ///
+    PinnedAuthorMismatch{ author: String },
+    ///
+    /// The message is signed by a publisher other than the author of the channel
+    ///
+    ForeignPublisher{ msg_id: String, publisher: String },
+    ///
+    /// More than one message is attached at the address of the message: the chain is forked
+    ///
+    Fork{ msg_id: String, copies: usize },
+    ///
+    /// The message was already seen by the reader, or a message seen by the reader is linked to it,
+    /// but it is not reachable from the announcement
+    ///
+    Gap{ msg_id: String },
+    ///
+    /// The message is linked to a message that is not reachable from the announcement
+    ///
+    BrokenLink{ msg_id: String, prev_msg_id: String },
+    ///
+    /// The message is linked to an earlier message of the chain instead of the previous one.
+    /// It is reported only for the single branch channels
+    ///
+    Discontinuity{ msg_id: String, expected_prev_msg_id: String, prev_msg_id: String },
+    ///
+    /// The copies of the message attached at its address could not be fetched, so the fork check was not done
+    ///
+    Unchecked{ msg_id: String, error: String },
+    ///
+    /// The message was seen by the reader in a different position of the chain
+    ///
+    OutOfOrder{ msg_id: String, expected_seq: usize, found_seq: usize },
+    ///
+    /// The state message restores the channel at the same message of a previous one
+    ///
+    DuplicateState{ msg_id: String, previous_msg_id: String },
+}
+
+///
+/// Message checked while verifying the chain
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CheckedMsg{
+    pub msg_id: String,
+    pub seq: usize,
+    pub publisher: String,
+    pub is_state: bool,
+    pub prev_msg_id: String,
+}
+
+///
+/// Result of the verification of the chain of a channel, from the announcement to the last message
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainReport{
+    pub channel_address: String,
+    pub announcement_id: String,
+    pub author_public_key: String,
+    pub checked_at: u64,
+    pub msgs: Vec<CheckedMsg>,
+    pub issues: Vec<ChainIssue>,
+}
+
+impl ChainReport{
+    ///
+    /// The chain is valid if no issue was found
+    ///
+    pub fn is_valid(&self) -> bool{
+        self.issues.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String>{
+        Ok(serde_json::to_string_pretty(&self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<ChainReport>{
+        Ok(serde_json::from_str(json)?)
+    }
+}
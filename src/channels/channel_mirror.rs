@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::channels::ChannelReader;
+use crate::channels::channel_state::current_timestamp;
+use crate::channels::received_msg::ReceivedMsg;
+use crate::storage::mirror_store::{MirrorStore, MirroredMsg};
+use crate::transport::channel_transport::ChannelTransport;
+
+///
+/// Mirror of the messages fetched by the readers into a local MirrorStore,
+/// with their position, the message they are linked to and the publisher.
+/// The mirrored history can be queried offline through the store.
+///
+#[derive(Clone)]
+pub struct ChannelMirror{
+    store: Arc<dyn MirrorStore>,
+}
+
+impl ChannelMirror{
+    pub fn new(store: Arc<dyn MirrorStore>) -> ChannelMirror{
+        ChannelMirror{
+            store
+        }
+    }
+
+    ///
+    /// Fetch the new messages of the reader from the node and mirror them, with the unread ones.
+    /// The messages are not consumed: they are still returned by the next fetch of the reader
+    ///
+    /// # Return Value
+    /// It returns the number of messages added to the store
+    ///
+    pub async fn sync<Trans: ChannelTransport>(&self, reader: &mut ChannelReader<Trans>) -> Result<usize>{
+        reader.fetch_new_msgs().await;
+        let (channel_id, _) = reader.channel_address();
+        self.record(&channel_id, reader.unread_msgs())
+    }
+
+    ///
+    /// Get all the mirrored messages of a channel, in the order of the chain
+    ///
+    pub fn messages(&self, channel_id: &str) -> Result<Vec<MirroredMsg>>{
+        self.store.messages(channel_id)
+    }
+
+    pub fn store(&self) -> Arc<dyn MirrorStore>{
+        self.store.clone()
+    }
+}
+
+impl ChannelMirror{
+    pub(crate) fn record(&self, channel_id: &str, msgs: &[ReceivedMsg]) -> Result<usize>{
+        let mut last = self.store.last(channel_id)?;
+        let mut added = 0;
+        for msg in msgs{
+            if self.store.get(channel_id, &msg.msg_id)?.is_some(){
+                continue;
+            }
+
+            let mirrored = MirroredMsg{
+                channel_id: channel_id.to_string(),
+                msg_id: msg.msg_id.clone(),
+                seq: last.as_ref().map(|m| m.seq + 1).unwrap_or(0),
+                prev_msg_id: msg.prev_msg_id.clone(),
+                publisher: msg.publisher.clone(),
+                public: msg.public.clone(),
+                masked: msg.masked.clone(),
+                fetched_at: current_timestamp(),
+            };
+            if self.store.insert(&mirrored)?{
+                added += 1;
+            }
+            last = Some(mirrored);
+        }
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::ChannelWriter;
+    use crate::payload::payload_serializers::JsonPacketBuilder;
+    use crate::storage::memory_mirror_store::MemoryMirrorStore;
+    use crate::transport::memory_transport::MemoryTransport;
+
+    #[tokio::test]
+    async fn mirrored_msgs_keep_the_link_of_the_channel() {
+        let transport = MemoryTransport::new();
+        let mut channel = ChannelWriter::builder().build_with_transport(transport.clone());
+        let (channel_address, announcement_id) = channel.open().await.unwrap();
+        let mut reader = ChannelReader::builder().build_with_transport(&channel_address, &announcement_id, transport.clone());
+        reader.attach().await.unwrap();
+
+        let packet = JsonPacketBuilder::new().public(&"first".to_string()).unwrap().build();
+        let first_id = channel.send_signed_packet(&packet).await.unwrap();
+        assert_eq!(reader.fetch_raw_msgs().await.len(), 1);
+
+        let mirror = ChannelMirror::new(Arc::new(MemoryMirrorStore::new()));
+        let packet = JsonPacketBuilder::new().public(&"second".to_string()).unwrap().build();
+        let second_id = channel.send_signed_packet(&packet).await.unwrap();
+        assert_eq!(mirror.sync(&mut reader).await.unwrap(), 1);
+
+        let msgs = mirror.messages(&channel_address).unwrap();
+        assert_eq!(msgs[0].msg_id, second_id);
+        assert_eq!(msgs[0].prev_msg_id, Some(first_id));
+    }
+}
@@ -1,55 +1,263 @@
-use std::fs::OpenOptions;
-use std::io::{Read, Write};
+use std::fs::{self, OpenOptions};
+use std::io::Read;
+use std::time::SystemTime;
 
-use aead::generic_array::GenericArray;
 use anyhow::Result;
 use base64::{decode_config, encode_config, URL_SAFE_NO_PAD};
-use chacha20poly1305::aead::{Aead, NewAead};
-use chacha20poly1305::XChaCha20Poly1305;
 use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+use std::fmt::{Debug, Formatter};
 
 use crate::utility::iota_utility::hash_string;
+use crate::payload::payload_serializers::RawPacket;
+use crate::user_builders::network::Network;
+use crate::channels::received_msg::ReceivedMsg;
+use crate::utility::file_utility::{atomic_write, backup_generations, backup_path, lock_file};
+use crate::utility::secrets::SecretString;
+use crate::user_builders::node_pool::{FailoverStrategy, NodePool};
+use iota_streams::app::transport::tangle::client::SendOptions;
+pub(crate) use crate::channels::state_encryption::{decrypt_bytes, encrypt_bytes};
+use iota_streams::app_channels::api::tangle::{Author, Subscriber};
+use futures::executor::block_on;
+use crate::transport::memory_transport::MemoryTransport;
 
-#[derive(Serialize, Deserialize, Debug)]
+///
+/// Number of backup generations kept by `write_to_file`
+///
+pub const DEFAULT_BACKUPS: usize = 1;
+
+///
+/// Version of the layout of the serialized states
+///
+pub const STATE_FORMAT_VERSION: u8 = 1;
+
+const STATE_MAGIC: &[u8] = b"CHST";
+const HEADER_SEPARATOR: u8 = b'.';
+
+///
+/// State of a channel. The user state is wiped from memory when dropped and never printed
+///
+#[derive(Serialize, Deserialize)]
 pub struct ChannelState{
     user_state: Vec<u8>,
     channel_id: String,
     announcement_id: String,
     last_msg_id: String,
+    network: Network,
+    channel_type: Option<String>,
+    created_at: u64,
+    modified_at: u64,
+    role: ChannelRole,
+    unread_msgs: Vec<ReceivedMsg>,
+    last_snapshot_id: Option<String>,
+    nodes: Vec<String>,
+    failover_strategy: FailoverStrategy,
+    #[serde(skip, default = "current_format_version")]
+    format_version: u8,
+}
+
+///
+/// Role of the user whose state is stored.
+/// The state of a reader records the id of its subscription message, its read cursor,
+/// the id of the last message returned to the application, and the public key of the pinned author
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ChannelRole{
+    Writer,
+    Reader{
+        subscription_id: Option<String>,
+        read_cursor: Option<String>,
+        pinned_author: Option<String>,
+    },
+}
+
+///
+/// Information about a state that can be shown without exposing the user state
+///
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChannelStateInfo{
+    pub channel_address: String,
+    pub announcement_id: String,
+    pub last_msg_id: String,
+    pub channel_type: Option<String>,
+    pub role: ChannelRole,
+    pub network: Network,
+    pub created_at: u64,
+    pub modified_at: u64,
+    pub last_snapshot_id: Option<String>,
+    pub nodes: Vec<String>,
+    pub failover_strategy: FailoverStrategy,
+    pub format_version: u8,
+}
+
+///
+/// Unencrypted header that can be prepended to an encrypted state
+///
+#[derive(Serialize, Deserialize)]
+struct StateHeader{
+    channel_id: String,
+}
+
+///
+/// Layout of the states exported before the format version was recorded (format version 0): they were always on the testnet
+///
+#[derive(Deserialize)]
+struct LegacyChannelStateV0{
+    user_state: Vec<u8>,
+    channel_id: String,
+    announcement_id: String,
+    last_msg_id: String,
 }
 
 impl ChannelState {
-    pub fn new(author_state: &Vec<u8>, channel_id: &str, announcement_id: &str, last_public_msg: &str) -> ChannelState{
+    pub fn new(author_state: &Vec<u8>, channel_id: &str, announcement_id: &str, last_public_msg: &str, network: &Network) -> ChannelState{
+        let now = current_timestamp();
         ChannelState{
             user_state: author_state.clone(),
             channel_id: channel_id.to_string(),
             announcement_id: announcement_id.to_string(),
             last_msg_id: last_public_msg.to_string(),
+            network: network.clone(),
+            channel_type: None,
+            created_at: now,
+            modified_at: now,
+            role: ChannelRole::Writer,
+            unread_msgs: Vec::new(),
+            last_snapshot_id: None,
+            nodes: Vec::new(),
+            failover_strategy: FailoverStrategy::RoundRobin,
+            format_version: STATE_FORMAT_VERSION,
         }
     }
 
-    pub fn from_file(file_path: &str, psw: &str) -> Result<ChannelState>{
-        let mut fr = OpenOptions::new().read(true).open(file_path)?;
-        let mut input = vec![];
-        fr.read_to_end(&mut input)?;
-        Ok(ChannelState::decrypt(&input, psw)?)
+    ///
+    /// Create the state of a reader, with the id of its subscription message and its read cursor
+    ///
+    pub fn new_reader(subscriber_state: &Vec<u8>, channel_id: &str, announcement_id: &str, subscription_id: Option<&str>, read_cursor: Option<&str>, network: &Network) -> ChannelState{
+        let mut state = ChannelState::new(subscriber_state, channel_id, announcement_id, "", network);
+        state.role = ChannelRole::Reader{
+            subscription_id: subscription_id.map(|id| id.to_string()),
+            read_cursor: read_cursor.map(|id| id.to_string()),
+            pinned_author: None,
+        };
+        state
+    }
+
+    ///
+    /// Set the type of the channel of an author state
+    ///
+    pub fn with_channel_type(mut self, channel_type: &str) -> Self{
+        self.channel_type = Some(channel_type.to_string());
+        self
+    }
+
+    ///
+    /// Set the creation time (seconds from the unix epoch) of the channel, when the state is exported again
+    ///
+    pub fn with_created_at(mut self, created_at: u64) -> Self{
+        self.created_at = created_at;
+        self
+    }
+
+    ///
+    /// Read the state from a file. Use `from_file_or_backup` to restore it from the backups when the file is missing or corrupted
+    ///
+    pub fn from_file(file_path: &str, psw: impl Into<SecretString>) -> Result<ChannelState>{
+        let psw = psw.into();
+        read_state_file(file_path, psw.expose())
+    }
+
+    ///
+    /// Read the state from a file or, if it is missing or corrupted, from the most recent backup generation that can be read.
+    ///
+    /// # Return Value
+    /// It returns the state and the generation it was loaded from: 0 is the file itself, 1 its most recent backup
+    ///
+    pub fn from_file_or_backup(file_path: &str, psw: impl Into<SecretString>) -> Result<(ChannelState, usize)>{
+        let psw = psw.into();
+        let err = match read_state_file(file_path, psw.expose()){
+            Ok(state) => return Ok((state, 0)),
+            Err(e) => e
+        };
+
+        for generation in backup_generations(file_path)? {
+            if let Ok(state) = read_state_file(&backup_path(file_path, generation), psw.expose()){
+                return Ok((state, generation));
+            }
+        }
+        Err(err)
     }
 }
 
 impl ChannelState{
-    pub fn write_to_file(&self, file_path: &str, psw: &str) -> Result<()>{
-        let mut fr = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(file_path)?;
-
-        fr.write_all(&self.encrypt(psw)?)?;
+    ///
+    /// Write the encrypted state in a file, keeping the previous content as backup
+    ///
+    pub fn write_to_file(&self, file_path: &str, psw: impl Into<SecretString>) -> Result<()>{
+        let psw = psw.into();
+        self.write_to_file_with_backups(file_path, psw.expose(), DEFAULT_BACKUPS)
+    }
+
+    ///
+    /// Write the encrypted state in a file, keeping up to `backups` generations of the previous content.
+    /// The file is replaced atomically and it is locked, so two processes can't write it at the same time
+    ///
+    pub fn write_to_file_with_backups(&self, file_path: &str, psw: impl Into<SecretString>, backups: usize) -> Result<()>{
+        let psw = psw.into();
+        let _lock = lock_file(file_path)?;
+        atomic_write(file_path, &self.encrypt(psw.expose())?, backups)
+    }
+
+    ///
+    /// Re-encrypt the user state, exported with the old password, with a new password.
+    /// It works offline: the user is imported on a memory transport, so no node is contacted.
+    /// Use `encrypt` or `write_to_file` with the new password to update the outer encryption layer too
+    ///
+    pub fn change_password(&mut self, old_psw: impl Into<SecretString>, new_psw: impl Into<SecretString>) -> Result<()>{
+        let old_psw = old_psw.into();
+        let new_psw = new_psw.into();
+        let old_hash = SecretString::from(hash_string(old_psw.expose()));
+        let new_hash = SecretString::from(hash_string(new_psw.expose()));
+        let user_state = block_on(reencrypt_user_state(&self.user_state, &old_hash, &new_hash))?;
+
+        self.user_state.zeroize();
+        self.user_state = user_state;
+        self.modified_at = current_timestamp();
         Ok(())
     }
 
-    pub fn user_state(&self) -> Vec<u8> {
-        self.user_state.clone()
+    ///
+    /// Change the password of a state file, re-encrypting both the user state and the file.
+    /// The backup generations are re-encrypted too; the ones that can't be read with the old password are deleted,
+    /// so no copy of the state protected by the old password is left
+    ///
+    pub fn change_file_password(file_path: &str, old_psw: impl Into<SecretString>, new_psw: impl Into<SecretString>) -> Result<()>{
+        let old_psw = old_psw.into();
+        let new_psw = new_psw.into();
+        let _lock = lock_file(file_path)?;
+        let mut channel_state = ChannelState::from_file(file_path, old_psw.expose())?;
+        channel_state.change_password(old_psw.expose(), new_psw.expose())?;
+
+        for generation in backup_generations(file_path)? {
+            let path = backup_path(file_path, generation);
+            match read_state_file(&path, old_psw.expose()){
+                Ok(mut backup) => {
+                    backup.change_password(old_psw.expose(), new_psw.expose())?;
+                    atomic_write(&path, &backup.encrypt(new_psw.expose())?, 0)?;
+                },
+                Err(_) => fs::remove_file(&path)?
+            }
+        }
+
+        let enc = match ChannelState::read_file_header(file_path){
+            Ok(Some(_)) => channel_state.encrypt_with_header(new_psw.expose())?,
+            _ => channel_state.encrypt(new_psw.expose())?
+        };
+        atomic_write(file_path, &enc, 0)
+    }
+
+    pub fn user_state(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.user_state.clone())
     }
     pub fn channel_id(&self) -> String {
         self.channel_id.to_string()
@@ -60,47 +268,436 @@ impl ChannelState{
     pub fn last_msg_id(&self) -> String {
         self.last_msg_id.clone()
     }
+    pub fn network(&self) -> Network {
+        self.network.clone()
+    }
+    pub fn channel_type(&self) -> Option<String> {
+        self.channel_type.clone()
+    }
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+    pub fn modified_at(&self) -> u64 {
+        self.modified_at
+    }
+    pub fn format_version(&self) -> u8 {
+        self.format_version
+    }
+    pub fn role(&self) -> ChannelRole {
+        self.role.clone()
+    }
+    pub fn is_reader(&self) -> bool {
+        matches!(self.role, ChannelRole::Reader{..})
+    }
+    pub fn subscription_id(&self) -> Option<String> {
+        match &self.role{
+            ChannelRole::Reader{ subscription_id, .. } => subscription_id.clone(),
+            ChannelRole::Writer => None
+        }
+    }
+    pub fn read_cursor(&self) -> Option<String> {
+        match &self.role{
+            ChannelRole::Reader{ read_cursor, .. } => read_cursor.clone(),
+            ChannelRole::Writer => None
+        }
+    }
+    pub fn pinned_author(&self) -> Option<String> {
+        match &self.role{
+            ChannelRole::Reader{ pinned_author, .. } => pinned_author.clone(),
+            ChannelRole::Writer => None
+        }
+    }
+    pub fn last_snapshot_id(&self) -> Option<String> {
+        self.last_snapshot_id.clone()
+    }
+    pub fn nodes(&self) -> Vec<String> {
+        self.nodes.clone()
+    }
+    pub fn failover_strategy(&self) -> FailoverStrategy {
+        self.failover_strategy
+    }
+
+    ///
+    /// Create the pool used to reconnect the restored user: the nodes and the strategy recorded in the state,
+    /// or the default nodes of the network. The specified node and send options take precedence
+    ///
+    pub fn node_pool(&self, node_url: Option<&str>, send_options: Option<SendOptions>) -> NodePool{
+        let nodes: Vec<&str> = self.nodes.iter().map(|n| n.as_str()).collect();
+        NodePool::from_network(&self.network)
+            .with_nodes(&nodes)
+            .strategy(self.failover_strategy)
+            .override_with(node_url, send_options)
+    }
+
+    ///
+    /// Get the information about the state, without the user state
+    ///
+    pub fn info(&self) -> ChannelStateInfo{
+        ChannelStateInfo{
+            channel_address: self.channel_id.clone(),
+            announcement_id: self.announcement_id.clone(),
+            last_msg_id: self.last_msg_id.clone(),
+            channel_type: self.channel_type.clone(),
+            role: self.role.clone(),
+            network: self.network.clone(),
+            created_at: self.created_at,
+            modified_at: self.modified_at,
+            last_snapshot_id: self.last_snapshot_id.clone(),
+            nodes: self.nodes.clone(),
+            failover_strategy: self.failover_strategy,
+            format_version: self.format_version,
+        }
+    }
+
+    ///
+    /// Get the information about a state file without connecting to the tangle
+    ///
+    pub fn inspect(file_path: &str, psw: impl Into<SecretString>) -> Result<ChannelStateInfo>{
+        let psw = psw.into();
+        Ok(ChannelState::from_file(file_path, psw.expose())?.info())
+    }
+
+    ///
+    /// Get the channel id from the unencrypted header of a state file, if it has one. The password is not needed
+    ///
+    pub fn read_file_header(file_path: &str) -> Result<Option<String>>{
+        let mut fr = OpenOptions::new().read(true).open(file_path)?;
+        let mut input = vec![];
+        fr.read_to_end(&mut input)?;
+        ChannelState::read_header(&input)
+    }
+
+    ///
+    /// Get the channel id from the unencrypted header of an exported state, if it has one
+    ///
+    pub fn read_header(input: &[u8]) -> Result<Option<String>>{
+        let (header, _) = split_header(input);
+        match header{
+            None => Ok(None),
+            Some(header) => {
+                let header: StateHeader = serde_json::from_slice(&decode_config(header, URL_SAFE_NO_PAD)?)?;
+                Ok(Some(header.channel_id))
+            }
+        }
+    }
+}
+
+impl Drop for ChannelState{
+    fn drop(&mut self) {
+        self.user_state.zeroize();
+    }
+}
+
+impl Debug for ChannelState{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChannelState")
+            .field("user_state", &"***")
+            .field("channel_id", &self.channel_id)
+            .field("announcement_id", &self.announcement_id)
+            .field("last_msg_id", &self.last_msg_id)
+            .field("network", &self.network)
+            .field("channel_type", &self.channel_type)
+            .field("created_at", &self.created_at)
+            .field("modified_at", &self.modified_at)
+            .field("role", &self.role)
+            .field("unread_msgs", &self.unread_msgs.len())
+            .field("last_snapshot_id", &self.last_snapshot_id)
+            .field("nodes", &self.nodes)
+            .field("failover_strategy", &self.failover_strategy)
+            .field("format_version", &self.format_version)
+            .finish()
+    }
 }
 
 impl ChannelState{
-    pub fn encrypt(&self, psw: &str) -> Result<Vec<u8>>{
-        let bytes = bincode::serialize(&self)?;
+    pub fn encrypt(&self, psw: impl Into<SecretString>) -> Result<Vec<u8>>{
+        let psw = psw.into();
+        encrypt_bytes(&self.to_bytes()?, psw.expose(), "Error during state encryption")
+    }
 
-        let (key, nonce) = get_key_nonce(psw);
-        let key = GenericArray::from_slice(&key[..]);
-        let nonce = GenericArray::from_slice(&nonce[..]);
+    ///
+    /// Encrypt the state and prepend an unencrypted header containing only the channel id,
+    /// so the channel can be identified without the password
+    ///
+    pub fn encrypt_with_header(&self, psw: impl Into<SecretString>) -> Result<Vec<u8>>{
+        let psw = psw.into();
+        let header = serde_json::to_vec(&StateHeader{ channel_id: self.channel_id.clone() })?;
+        let mut res = encode_config(&header, URL_SAFE_NO_PAD).as_bytes().to_vec();
+        res.push(HEADER_SEPARATOR);
+        res.extend(self.encrypt(psw.expose())?);
+        Ok(res)
+    }
 
-        let chacha = XChaCha20Poly1305::new(key);
-        let enc = match chacha.encrypt(nonce, bytes.as_ref()){
-            Ok(res) => res,
-            Err(_) => return Err(anyhow::Error::msg("Error during state encryption")),
-        };
-        let base64 = encode_config(&enc, URL_SAFE_NO_PAD);
-        Ok(base64.as_bytes().to_vec())
+    pub fn decrypt(input: &[u8], psw: impl Into<SecretString>) -> Result<ChannelState>{
+        let psw = psw.into();
+        let (_, encrypted) = split_header(input);
+        let dec = decrypt_bytes(encrypted, psw.expose(), "Error during state decryption")?;
+        ChannelState::from_bytes(&dec)
     }
 
-    pub fn decrypt(input: &[u8], psw: &str) -> Result<ChannelState>{
-        let bytes = decode_config(input, URL_SAFE_NO_PAD)?;
+    ///
+    /// Write the encrypted state in a file with the unencrypted header, keeping the previous content as backup
+    ///
+    pub fn write_to_file_with_header(&self, file_path: &str, psw: impl Into<SecretString>) -> Result<()>{
+        let psw = psw.into();
+        let _lock = lock_file(file_path)?;
+        atomic_write(file_path, &self.encrypt_with_header(psw.expose())?, DEFAULT_BACKUPS)
+    }
+}
 
-        let (key, nonce) = get_key_nonce(psw);
-        let key = GenericArray::from_slice(&key[..]);
-        let nonce = GenericArray::from_slice(&nonce[..]);
+impl ChannelState{
+    ///
+    /// Keep the messages fetched by a reader but not yet returned to the application
+    ///
+    pub(crate) fn with_unread_msgs(mut self, unread_msgs: Vec<ReceivedMsg>) -> Self{
+        self.unread_msgs = unread_msgs;
+        self
+    }
 
-        let chacha = XChaCha20Poly1305::new(key);
-        let dec = match chacha.decrypt(nonce, bytes.as_ref()){
-            Ok(res) => res,
-            Err(_) => return Err(anyhow::Error::msg("Error during state decryption")),
-        };
+    pub(crate) fn unread_msgs(&self) -> Vec<ReceivedMsg>{
+        self.unread_msgs.clone()
+    }
+
+    ///
+    /// Record the id of the last state message published by the writer, so it can be restored without walking the channel
+    ///
+    pub(crate) fn with_last_snapshot_id(mut self, last_snapshot_id: Option<&str>) -> Self{
+        self.last_snapshot_id = last_snapshot_id.map(|id| id.to_string());
+        self
+    }
+
+    ///
+    /// Record the public key of the author pinned by a reader, that is checked again on import
+    ///
+    pub(crate) fn with_pinned_author(mut self, public_key: Option<&str>) -> Self{
+        if let ChannelRole::Reader{ pinned_author, .. } = &mut self.role{
+            *pinned_author = public_key.map(|pk| pk.to_string());
+        }
+        self
+    }
+
+    ///
+    /// Record the nodes and the failover strategy of the pool of the user, so they are used again on import
+    ///
+    pub(crate) fn with_node_pool(mut self, node_pool: &NodePool) -> Self{
+        self.nodes = node_pool.nodes().to_vec();
+        self.failover_strategy = node_pool.failover_strategy();
+        self
+    }
+
+    ///
+    /// Serialize the state preceded by its format version
+    ///
+    pub(crate) fn to_bytes(&self) -> Result<Zeroizing<Vec<u8>>>{
+        let mut bytes = Zeroizing::new(STATE_MAGIC.to_vec());
+        bytes.push(STATE_FORMAT_VERSION);
+        bytes.extend_from_slice(&Zeroizing::new(bincode::serialize(&self)?));
+        Ok(bytes)
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<ChannelState>{
+        if bytes.starts_with(STATE_MAGIC) && bytes.len() > STATE_MAGIC.len(){
+            let version = bytes[STATE_MAGIC.len()];
+            let body = &bytes[STATE_MAGIC.len() + 1..];
+            return match version{
+                STATE_FORMAT_VERSION => Ok(bincode::deserialize::<ChannelState>(body)?),
+                _ => Err(anyhow::Error::msg(format!("Unsupported state format version {}", version)))
+            };
+        }
+
+        let mut legacy: LegacyChannelStateV0 = bincode::deserialize(bytes)?;
+        let mut state = ChannelState::new(&std::mem::take(&mut legacy.user_state), &legacy.channel_id, &legacy.announcement_id, &legacy.last_msg_id, &Network::Testnet);
+        state.created_at = 0;
+        state.modified_at = 0;
+        state.format_version = 0;
+        Ok(state)
+    }
+}
+
+fn current_format_version() -> u8{
+    STATE_FORMAT_VERSION
+}
+
+///
+/// Get the seconds from the unix epoch
+///
+pub(crate) fn current_timestamp() -> u64{
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH){
+        Ok(value) => value.as_secs(),
+        Err(_) => 0
+    }
+}
+
+fn split_header(input: &[u8]) -> (Option<&[u8]>, &[u8]){
+    match input.iter().position(|b| *b == HEADER_SEPARATOR){
+        None => (None, input),
+        Some(idx) => (Some(&input[..idx]), &input[idx + 1..])
+    }
+}
+
+///
+/// Get the public part of the messages that contain the encrypted state of the channels
+///
+pub(crate) fn state_msg_tag(channel_id: &str, announcement_id: &str) -> String{
+    format!("{}:{}.state", channel_id, announcement_id)
+}
+
+///
+/// Get the encrypted state contained in a message payload, if the message is a state message of the channels
+///
+pub(crate) fn parse_state_msg(channel_id: &str, announcement_id: &str, public: &[u8], masked: &[u8]) -> Option<Vec<u8>>{
+    let packet = RawPacket::from_streams_response(public, masked, None).ok()?;
+    let (tag, state): (String, Vec<u8>) = packet.deserialize().ok()?;
+    match tag == state_msg_tag(channel_id, announcement_id){
+        true => Some(state),
+        false => None
+    }
+}
+
+fn read_state_file(file_path: &str, psw: &str) -> Result<ChannelState>{
+    let mut fr = OpenOptions::new().read(true).open(file_path)?;
+    let mut input = vec![];
+    fr.read_to_end(&mut input)?;
+    ChannelState::decrypt(&input, psw)
+}
 
-        let ch_state: ChannelState = bincode::deserialize(&dec)?;
-        Ok(ch_state)
+///
+/// Export the user state, of an author or a subscriber, with a new password.
+/// The user is imported on a memory transport, since the import and the export never use it
+///
+async fn reencrypt_user_state(user_state: &[u8], old_hash: &SecretString, new_hash: &SecretString) -> Result<Vec<u8>>{
+    match Author::import(user_state, old_hash.expose(), MemoryTransport::new()).await{
+        Ok(author) => author.export(new_hash.expose()).await,
+        Err(_) => match Subscriber::import(user_state, old_hash.expose(), MemoryTransport::new()).await{
+            Ok(subscriber) => subscriber.export(new_hash.expose()).await,
+            Err(_) => Err(anyhow::Error::msg("Wrong password for the user state"))
+        }
     }
 }
 
-fn get_key_nonce(psw: &str) -> (Vec<u8>, Vec<u8>) {
-    let key_hash = &hash_string(psw)[..32];
-    let nonce_hash = &hash_string(key_hash)[..24];
-    let key = key_hash.as_bytes();
-    let nonce = nonce_hash.as_bytes();
-    (key.to_vec(), nonce.to_vec())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iota_streams::app::transport::BucketTransport;
+    use iota_streams::app_channels::api::ChannelType;
+    use crate::utility::iota_utility::random_seed;
+
+    async fn writer_state(psw: &str, last_msg_id: &str) -> ChannelState{
+        let author = Author::new(&random_seed(), ChannelType::SingleBranch, BucketTransport::new());
+        let psw_hash = SecretString::from(hash_string(psw));
+        let user_state = author.export(psw_hash.expose()).await.unwrap();
+        ChannelState::new(&user_state, "channel", "announcement", last_msg_id, &Network::Devnet)
+    }
+
+    #[test]
+    fn state_roundtrip_keeps_the_fields() {
+        let state = ChannelState::new_reader(&b"user state".to_vec(), "channel", "announcement", Some("sub"), Some("cursor"), &Network::Devnet)
+            .with_pinned_author(Some("author"))
+            .with_last_snapshot_id(Some("snapshot"));
+        let dec = ChannelState::decrypt(&state.encrypt_with_header("psw").unwrap(), "psw").unwrap();
+        assert_eq!(dec.role(), state.role());
+        assert_eq!(dec.pinned_author(), Some("author".to_string()));
+        assert_eq!(dec.last_snapshot_id(), Some("snapshot".to_string()));
+        assert_eq!(dec.format_version(), STATE_FORMAT_VERSION);
+        assert!(ChannelState::decrypt(&state.encrypt("psw").unwrap(), "other").is_err());
+    }
+
+    #[test]
+    fn node_pool_is_kept_in_the_state() {
+        let pool = NodePool::new(&["http://primary", "http://backup"], FailoverStrategy::PrimaryBackup);
+        let state = ChannelState::new(&b"user state".to_vec(), "channel", "announcement", "", &Network::Devnet)
+            .with_node_pool(&pool);
+        let dec = ChannelState::decrypt(&state.encrypt("psw").unwrap(), "psw").unwrap();
+        let restored = dec.node_pool(None, None);
+        assert_eq!(restored.nodes(), pool.nodes());
+        assert_eq!(restored.failover_strategy(), FailoverStrategy::PrimaryBackup);
+        assert_eq!(dec.node_pool(Some("http://other"), None).nodes(), &["http://other".to_string()]);
+    }
+
+    #[test]
+    fn unversioned_state_is_read_on_the_testnet() {
+        let legacy = (b"user state".to_vec(), "channel".to_string(), "announcement".to_string(), "last".to_string());
+        let bytes = bincode::serialize(&legacy).unwrap();
+        let state = ChannelState::decrypt(&encrypt_bytes(&bytes, "psw", "err").unwrap(), "psw").unwrap();
+        assert_eq!(state.format_version(), 0);
+        assert_eq!(state.network(), Network::Testnet);
+        assert_eq!(state.last_msg_id(), "last");
+        assert_eq!(state.role(), ChannelRole::Writer);
+        assert_eq!(state.created_at(), 0);
+    }
+
+    #[tokio::test]
+    async fn change_file_password_re_encrypts_the_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("channel.state");
+        let file_path = file_path.to_str().unwrap();
+        writer_state("old", "first").await.write_to_file_with_backups(file_path, "old", 2).unwrap();
+        writer_state("old", "second").await.write_to_file_with_backups(file_path, "old", 2).unwrap();
+        writer_state("old", "third").await.write_to_file_with_backups(file_path, "old", 2).unwrap();
+        std::fs::write(backup_path(file_path, 3), b"unreadable").unwrap();
+
+        ChannelState::change_file_password(file_path, "old", "new").unwrap();
+
+        assert_eq!(read_state_file(file_path, "new").unwrap().last_msg_id(), "third");
+        assert_eq!(read_state_file(&backup_path(file_path, 1), "new").unwrap().last_msg_id(), "second");
+        assert_eq!(read_state_file(&backup_path(file_path, 2), "new").unwrap().last_msg_id(), "first");
+        assert!(read_state_file(file_path, "old").is_err());
+        assert!(read_state_file(&backup_path(file_path, 1), "old").is_err());
+        assert!(!std::path::Path::new(&backup_path(file_path, 3)).exists());
+    }
+
+    #[test]
+    fn from_file_reports_the_backup_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("channel.state");
+        let file_path = file_path.to_str().unwrap();
+        let state = ChannelState::new(&b"user state".to_vec(), "channel", "announcement", "first", &Network::Devnet);
+        state.write_to_file(file_path, "psw").unwrap();
+        state.write_to_file(file_path, "psw").unwrap();
+        assert_eq!(ChannelState::from_file_or_backup(file_path, "psw").unwrap().1, 0);
+
+        std::fs::write(file_path, b"corrupted").unwrap();
+        assert!(ChannelState::from_file(file_path, "psw").is_err());
+        let (restored, generation) = ChannelState::from_file_or_backup(file_path, "psw").unwrap();
+        assert_eq!(generation, 1);
+        assert_eq!(restored.last_msg_id(), "first");
+    }
+
+    #[test]
+    fn state_file_is_inspected_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("channel.state");
+        let file_path = file_path.to_str().unwrap();
+        ChannelState::new(&b"user state".to_vec(), "channel", "announcement", "last", &Network::Devnet)
+            .with_channel_type("single_branch")
+            .with_created_at(10)
+            .write_to_file_with_header(file_path, "psw").unwrap();
+
+        assert_eq!(ChannelState::read_file_header(file_path).unwrap(), Some("channel".to_string()));
+        let info = ChannelState::inspect(file_path, "psw").unwrap();
+        assert_eq!(info.channel_address, "channel");
+        assert_eq!(info.announcement_id, "announcement");
+        assert_eq!(info.last_msg_id, "last");
+        assert_eq!(info.channel_type, Some("single_branch".to_string()));
+        assert_eq!(info.network, Network::Devnet);
+        assert_eq!(info.created_at, 10);
+        assert!(info.modified_at >= info.created_at);
+        assert_eq!(info.format_version, STATE_FORMAT_VERSION);
+        assert!(ChannelState::inspect(file_path, "other").is_err());
+    }
+
+    #[test]
+    fn state_without_header_has_no_channel_id() {
+        let state = ChannelState::new(&b"user state".to_vec(), "channel", "announcement", "last", &Network::Devnet);
+        assert_eq!(ChannelState::read_header(&state.encrypt("psw").unwrap()).unwrap(), None);
+    }
+
+    #[test]
+    fn newer_format_is_rejected() {
+        let mut bytes = STATE_MAGIC.to_vec();
+        bytes.push(STATE_FORMAT_VERSION + 1);
+        bytes.extend(b"state");
+        let err = ChannelState::decrypt(&encrypt_bytes(&bytes, "psw", "err").unwrap(), "psw").unwrap_err();
+        assert!(err.to_string().contains("Unsupported state format version"));
+    }
 }
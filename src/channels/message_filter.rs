@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+use crate::channels::received_msg::ReceivedMsg;
+use crate::utility::iota_utility::public_key_hex_matches;
+
+///
+/// Filter used by `ChannelReader::fetch_filtered` to select the messages, working on their deserialized public data `P`.
+/// A message is selected when it matches all the configured conditions
+///
+pub struct MessageFilter<P>{
+    predicate: Option<Box<dyn Fn(&P) -> bool + Send + Sync>>,
+    publisher: Option<String>,
+    from_msg: Option<String>,
+    to_msg: Option<String>,
+    time_range: Option<(u64, u64)>,
+}
+
+impl<P> MessageFilter<P>{
+    ///
+    /// Select the messages whose public data satisfies the predicate
+    ///
+    pub fn new<F>(predicate: F) -> MessageFilter<P>
+    where
+        F: Fn(&P) -> bool + Send + Sync + 'static
+    {
+        MessageFilter::all().predicate(predicate)
+    }
+
+    ///
+    /// Select all the messages whose public data can be deserialized
+    ///
+    pub fn all() -> MessageFilter<P>{
+        MessageFilter{
+            predicate: None,
+            publisher: None,
+            from_msg: None,
+            to_msg: None,
+            time_range: None,
+        }
+    }
+
+    pub fn predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&P) -> bool + Send + Sync + 'static
+    {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    ///
+    /// Select the messages signed by the publisher with the specified hex encoded public key or fingerprint
+    ///
+    pub fn publisher(mut self, public_key: &str) -> Self{
+        self.publisher = Some(public_key.to_string());
+        self
+    }
+
+    ///
+    /// Select the messages starting from the specified message id, included
+    ///
+    pub fn from_msg(mut self, msg_id: &str) -> Self{
+        self.from_msg = Some(msg_id.to_string());
+        self
+    }
+
+    ///
+    /// Select the messages up to the specified message id, included
+    ///
+    pub fn to_msg(mut self, msg_id: &str) -> Self{
+        self.to_msg = Some(msg_id.to_string());
+        self
+    }
+
+    ///
+    /// Select the messages whose envelope has a timestamp, in milliseconds from the unix epoch, between `from` and `to`, included
+    ///
+    pub fn time_range(mut self, from: u64, to: u64) -> Self{
+        self.time_range = Some((from, to));
+        self
+    }
+}
+
+impl<P> MessageFilter<P>{
+    pub(crate) fn from_msg_id(&self) -> Option<&str>{
+        self.from_msg.as_deref()
+    }
+
+    pub(crate) fn has_time_range(&self) -> bool{
+        self.time_range.is_some()
+    }
+
+    ///
+    /// Get the positions of the messages inside the message id range, in the order of the chain.
+    /// The range is empty if the first message is not found
+    ///
+    pub(crate) fn range(&self, msgs: &[ReceivedMsg]) -> Range<usize>{
+        let start = match &self.from_msg{
+            None => 0,
+            Some(from_msg) => match msgs.iter().position(|msg| &msg.msg_id == from_msg){
+                Some(start) => start,
+                None => return msgs.len()..msgs.len()
+            }
+        };
+        let end = match &self.to_msg{
+            None => msgs.len(),
+            Some(to_msg) => match msgs[start..].iter().position(|msg| &msg.msg_id == to_msg){
+                Some(offset) => start + offset + 1,
+                None => msgs.len()
+            }
+        };
+        start..end
+    }
+
+    ///
+    /// Check the conditions on a message, its public data and the timestamp of its envelope, needed only with a time range
+    ///
+    pub(crate) fn matches(&self, msg: &ReceivedMsg, public: &P, timestamp: Option<u64>) -> bool{
+        if let Some(publisher) = &self.publisher{
+            if !public_key_hex_matches(&msg.publisher, publisher){
+                return false;
+            }
+        }
+
+        if let Some((from, to)) = self.time_range{
+            match timestamp{
+                Some(ts) if ts >= from && ts <= to => {},
+                _ => return false
+            }
+        }
+
+        match &self.predicate{
+            None => true,
+            Some(predicate) => predicate(public)
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use base64::{decode_config, encode_config, URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use iota_streams::app::message::BinaryMessage;
+use iota_streams::app::transport::{BucketTransport, Transport};
+use iota_streams::app_channels::api::tangle::{Address, Message as TangleMessage, MessageContent, Subscriber};
+use iota_streams::ddml::types::Bytes;
+
+use crate::utility::iota_utility::{create_link, public_key_hex, public_key_hex_matches, random_seed};
+
+///
+/// Binary message of a channel, as attached to the tangle
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProofMsg{
+    pub link: String,
+    pub prev_link: String,
+    pub body: String,
+    pub timestamp: u64,
+}
+
+///
+/// Self-contained proof that a message was published in a channel.
+/// It contains the binary messages from the announcement to the proved message, so it can be verified without a node
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MessageProof{
+    pub channel_address: String,
+    pub announcement_id: String,
+    pub msg_id: String,
+    pub publisher_key: String,
+    pub links: Vec<String>,
+    pub public_payload: Vec<u8>,
+    pub masked_payload: Vec<u8>,
+    pub messages: Vec<ProofMsg>,
+}
+
+impl MessageProof{
+    pub fn to_json(&self) -> Result<String>{
+        Ok(serde_json::to_string_pretty(&self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<MessageProof>{
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl ProofMsg{
+    pub(crate) fn from_message(msg: &TangleMessage) -> ProofMsg{
+        ProofMsg{
+            link: format!("{}:{}", msg.binary.link.appinst, msg.binary.link.msgid),
+            prev_link: format!("{}:{}", msg.binary.prev_link.appinst, msg.binary.prev_link.msgid),
+            body: encode_config(&msg.binary.body.0, URL_SAFE_NO_PAD),
+            timestamp: msg.timestamp,
+        }
+    }
+
+    pub(crate) fn to_message(&self) -> Result<TangleMessage>{
+        let link = parse_link(&self.link)?;
+        let prev_link = parse_link(&self.prev_link)?;
+        let body = Bytes(decode_config(&self.body, URL_SAFE_NO_PAD)?);
+        Ok(TangleMessage{
+            binary: BinaryMessage::new(link, prev_link, body),
+            timestamp: self.timestamp,
+        })
+    }
+}
+
+///
+/// Verify a proof without connecting to a node: the binary messages are unwrapped again from the announcement,
+/// checking their signatures and linkage, and the proved message must have the publisher and the payloads of the proof.
+/// The announcement must be signed by the expected author, identified by its hex encoded public key or fingerprint:
+/// anyone can build a valid proof for a channel of its own, so the proof is meaningful only for a known author
+///
+/// # Return Value
+/// It returns the hex encoded public key of the author of the channel
+///
+pub async fn verify_proof(proof: &MessageProof, author_key: &str) -> Result<String>{
+    let mut transport = BucketTransport::new();
+    for msg in &proof.messages{
+        transport.send_message(&msg.to_message()?).await?;
+    }
+
+    let mut subscriber = Subscriber::new(&random_seed(), transport);
+    let announce_link = create_link(&proof.channel_address, &proof.announcement_id)?;
+    subscriber.receive_announcement(&announce_link).await?;
+    let author = match subscriber.author_public_key(){
+        Some(pk) if public_key_hex_matches(&public_key_hex(pk), author_key) => public_key_hex(pk),
+        _ => return Err(anyhow::Error::msg("The announcement is not signed by the expected author"))
+    };
+
+    let mut links = vec![];
+    for msg in subscriber.fetch_all_next_msgs().await{
+        let msg_id = msg.link.msgid.to_string();
+        links.push(msg_id.clone());
+        if msg_id != proof.msg_id{
+            continue;
+        }
+
+        if links != proof.links{
+            return Err(anyhow::Error::msg("The chain of links doesn't match the proof"));
+        }
+        return match msg.body{
+            MessageContent::SignedPacket {pk, public_payload, masked_payload } => {
+                if public_key_hex(&pk) != proof.publisher_key{
+                    return Err(anyhow::Error::msg("The message is not signed by the publisher of the proof"));
+                }
+                if public_payload.0 != proof.public_payload || masked_payload.0 != proof.masked_payload{
+                    return Err(anyhow::Error::msg("The payloads don't match the proof"));
+                }
+                Ok(author)
+            },
+            _ => Err(anyhow::Error::msg("The proved message is not a signed packet"))
+        };
+    }
+    Err(anyhow::Error::msg("The proved message is not reachable from the announcement"))
+}
+
+fn parse_link(link: &str) -> Result<Address>{
+    let parts: Vec<&str> = link.splitn(2, ':').collect();
+    match parts.as_slice(){
+        [appinst, msgid] => create_link(appinst, msgid),
+        _ => Err(anyhow::Error::msg(format!("Invalid link: {}", link)))
+    }
+}
@@ -4,5 +4,36 @@ pub use tangle_channel_writer::ChannelWriter;
 mod tangle_channel_reader;
 pub use tangle_channel_reader::ChannelReader;
 
+mod batch_error;
+pub use batch_error::BatchSendError;
+
+mod unreachable_error;
+pub use unreachable_error::UnreachableNodeError;
+
+mod outbox;
+pub use outbox::Outbox;
+
+mod snapshot_policy;
+pub use snapshot_policy::SnapshotPolicy;
+
+mod autosave_policy;
+pub use autosave_policy::{AutosavePolicy, AutosaveTarget};
+
+mod message_filter;
+pub use message_filter::MessageFilter;
+
+mod channel_mirror;
+pub use channel_mirror::ChannelMirror;
+
+mod chain_report;
+pub use chain_report::{ChainIssue, ChainReport, CheckedMsg};
+
+mod message_proof;
+pub use message_proof::{MessageProof, ProofMsg, verify_proof};
+
+pub(crate) mod state_encryption;
+mod received_msg;
+mod msg_cache;
+
 pub mod channel_state;
 mod builders;
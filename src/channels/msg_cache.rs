@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use crate::channels::received_msg::ReceivedMsg;
+
+///
+/// Local cache of the messages of a channel, in the order of the chain
+///
+pub(crate) struct MsgCache{
+    msgs: Vec<ReceivedMsg>,
+    index: HashMap<String, usize>,
+}
+
+impl MsgCache{
+    pub(crate) fn new() -> MsgCache{
+        MsgCache{
+            msgs: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, msg: ReceivedMsg){
+        if self.index.contains_key(&msg.msg_id){
+            return;
+        }
+        self.index.insert(msg.msg_id.clone(), self.msgs.len());
+        self.msgs.push(msg);
+    }
+
+    pub(crate) fn get(&self, msg_id: &str) -> Option<&ReceivedMsg>{
+        self.index.get(msg_id).map(|idx| &self.msgs[*idx])
+    }
+
+    ///
+    /// Get the messages starting from the specified one, included
+    ///
+    pub(crate) fn from_msg(&self, msg_id: &str) -> Option<&[ReceivedMsg]>{
+        self.index.get(msg_id).map(|idx| &self.msgs[*idx..])
+    }
+
+    pub(crate) fn all(&self) -> &[ReceivedMsg]{
+        &self.msgs
+    }
+
+    pub(crate) fn len(&self) -> usize{
+        self.msgs.len()
+    }
+
+    pub(crate) fn clear(&mut self){
+        self.msgs.clear();
+        self.index.clear();
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Read;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::channels::channel_state::{ChannelState, decrypt_bytes, encrypt_bytes};
+use crate::utility::secrets::SecretString;
+use crate::utility::file_utility::{atomic_write, lock_file};
+use zeroize::{Zeroize, Zeroizing};
+
+#[derive(Serialize, Deserialize, Zeroize)]
+#[zeroize(drop)]
+struct OutboxSnapshot{
+    channel_state: Vec<u8>,
+    queue: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+///
+/// Persistent queue of the packets that could not be attached to the tangle.
+/// The queue is stored in the same encrypted file of the channel state, so that they are always saved together.
+///
+pub struct Outbox{
+    file_path: String,
+    psw: SecretString,
+    queue: VecDeque<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Outbox{
+    pub fn new(file_path: &str, psw: impl Into<SecretString>) -> Outbox{
+        let psw = psw.into();
+        Outbox{
+            file_path: file_path.to_string(),
+            psw,
+            queue: VecDeque::new(),
+        }
+    }
+
+    ///
+    /// Load the channel state and the pending packets from an outbox file
+    ///
+    pub fn load(file_path: &str, psw: impl Into<SecretString>) -> Result<(ChannelState, Outbox)>{
+        let psw = psw.into();
+        let mut fr = OpenOptions::new().read(true).open(file_path)?;
+        let mut input = vec![];
+        fr.read_to_end(&mut input)?;
+
+        let dec = decrypt_bytes(&input, psw.expose(), "Error during outbox decryption")?;
+        let mut snapshot: OutboxSnapshot = bincode::deserialize(&dec)?;
+        let channel_state = Zeroizing::new(std::mem::take(&mut snapshot.channel_state));
+        let outbox = Outbox{
+            file_path: file_path.to_string(),
+            psw,
+            queue: snapshot.queue.drain(..).collect(),
+        };
+        Ok((ChannelState::from_bytes(&channel_state)?, outbox))
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+}
+
+impl Outbox{
+    pub(crate) fn psw(&self) -> &str {
+        self.psw.expose()
+    }
+
+    pub(crate) fn push(&mut self, public: Vec<u8>, masked: Vec<u8>){
+        self.queue.push_back((public, masked));
+    }
+
+    pub(crate) fn front(&self) -> Option<&(Vec<u8>, Vec<u8>)>{
+        self.queue.front()
+    }
+
+    pub(crate) fn pop_front(&mut self){
+        self.queue.pop_front();
+    }
+
+    pub(crate) fn save(&self, channel_state: ChannelState) -> Result<()>{
+        let snapshot = OutboxSnapshot{
+            channel_state: channel_state.to_bytes()?.to_vec(),
+            queue: self.queue.iter().cloned().collect(),
+        };
+        let bytes = Zeroizing::new(bincode::serialize(&snapshot)?);
+        let enc = encrypt_bytes(&bytes, self.psw.expose(), "Error during outbox encryption")?;
+
+        let _lock = lock_file(&self.file_path)?;
+        atomic_write(&self.file_path, &enc, 0)
+    }
+}
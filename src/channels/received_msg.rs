@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+///
+/// Message fetched by a reader, with the hex encoded public key of its publisher and the id of the message it is linked to.
+/// The payloads are wiped from memory when dropped
+///
+#[derive(Serialize, Deserialize, Clone, Zeroize)]
+#[zeroize(drop)]
+pub(crate) struct ReceivedMsg{
+    pub(crate) msg_id: String,
+    pub(crate) publisher: String,
+    pub(crate) public: Vec<u8>,
+    pub(crate) masked: Vec<u8>,
+    pub(crate) prev_msg_id: Option<String>,
+}
+
+impl ReceivedMsg{
+    pub(crate) fn new(msg_id: &str, publisher: &str, public: Vec<u8>, masked: Vec<u8>, prev_msg_id: Option<&str>) -> ReceivedMsg{
+        ReceivedMsg{
+            msg_id: msg_id.to_string(),
+            publisher: publisher.to_string(),
+            public,
+            masked,
+            prev_msg_id: prev_msg_id.map(|id| id.to_string()),
+        }
+    }
+
+    pub(crate) fn to_raw(&self) -> (String, Vec<u8>, Vec<u8>){
+        (self.msg_id.clone(), self.public.clone(), self.masked.clone())
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+use crate::utility::secrets::SecretString;
+
+///
+/// Policy used to publish the encrypted state of the channels as a new message.
+/// A snapshot is published when at least one of the configured limits is reached.
+///
+#[derive(Clone)]
+pub struct SnapshotPolicy{
+    state_psw: SecretString,
+    every_msgs: Option<usize>,
+    every: Option<Duration>,
+}
+
+impl SnapshotPolicy{
+    ///
+    /// Create a policy that encrypts the snapshots with the specified password and never publishes them until a limit is set
+    ///
+    pub fn new(state_psw: impl Into<SecretString>) -> SnapshotPolicy{
+        let state_psw = state_psw.into();
+        SnapshotPolicy{
+            state_psw,
+            every_msgs: None,
+            every: None
+        }
+    }
+
+    ///
+    /// Publish a snapshot after the specified number of messages
+    ///
+    pub fn every_msgs(mut self, msgs: usize) -> Self{
+        self.every_msgs = Some(msgs.max(1));
+        self
+    }
+
+    ///
+    /// Publish a snapshot with the first message sent after the specified time from the last one
+    ///
+    pub fn every(mut self, interval: Duration) -> Self{
+        self.every = Some(interval);
+        self
+    }
+}
+
+impl SnapshotPolicy{
+    pub(crate) fn state_psw(&self) -> &str{
+        self.state_psw.expose()
+    }
+
+    pub(crate) fn is_due(&self, msgs_since_snapshot: usize, last_snapshot: Instant) -> bool{
+        let by_msgs = match self.every_msgs{
+            None => false,
+            Some(n) => msgs_since_snapshot >= n
+        };
+        let by_time = match self.every{
+            None => false,
+            Some(interval) => msgs_since_snapshot > 0 && last_snapshot.elapsed() >= interval
+        };
+        by_msgs || by_time
+    }
+}
@@ -0,0 +1,239 @@
+use aead::generic_array::GenericArray;
+use aead::Payload;
+use anyhow::Result;
+use base64::{decode_config, encode_config, URL_SAFE_NO_PAD};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::utility::iota_utility::hash_string;
+
+///
+/// Version of the layout of the encrypted envelopes. The envelopes without a version (version 0)
+/// were encrypted with a key and a nonce derived only from the password: they can still be decrypted
+///
+pub const ENCRYPTION_FORMAT_VERSION: u8 = 1;
+
+const ENVELOPE_MAGIC: &[u8] = b"CHSE";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = 4 + 1 + 1 + SALT_LEN;
+
+///
+/// Cost of scrypt as log2 of N. It is stored in each envelope, so it can be raised without breaking the old ones
+///
+#[cfg(not(test))]
+const KDF_LOG_N: u8 = 15;
+#[cfg(test)]
+const KDF_LOG_N: u8 = 10;
+const KDF_MAX_LOG_N: u8 = 20;
+const KDF_R: u32 = 8;
+const KDF_P: u32 = 1;
+
+///
+/// Key derived from a password with scrypt and a random salt.
+/// Each encryption uses a new random nonce, stored with the salt in front of the ciphertext:
+/// `magic | version | log_n | salt | nonce | ciphertext`, encoded in base64.
+/// The key is wiped from memory when dropped
+///
+pub(crate) struct StateKey{
+    key: Zeroizing<[u8; 32]>,
+    salt: [u8; SALT_LEN],
+    log_n: u8,
+}
+
+impl StateKey{
+    ///
+    /// Derive a key from the password with a new random salt
+    ///
+    pub(crate) fn generate(psw: &str) -> Result<StateKey>{
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        StateKey::derive(psw, &salt, KDF_LOG_N)
+    }
+
+    ///
+    /// Derive the key used by an envelope, reading the salt and the cost from its header
+    ///
+    pub(crate) fn for_envelope(input: &[u8], psw: &str) -> Result<StateKey>{
+        let bytes = decode_config(input, URL_SAFE_NO_PAD)?;
+        match parse_header(&bytes){
+            Some((salt, log_n)) => StateKey::derive(psw, &salt, log_n),
+            None => Err(anyhow::Error::msg("The data is not an encrypted envelope"))
+        }
+    }
+
+    fn derive(psw: &str, salt: &[u8; SALT_LEN], log_n: u8) -> Result<StateKey>{
+        if log_n > KDF_MAX_LOG_N{
+            return Err(anyhow::Error::msg(format!("Unsupported key derivation cost {}", log_n)));
+        }
+        let params = match scrypt::Params::new(log_n, KDF_R, KDF_P){
+            Ok(params) => params,
+            Err(_) => return Err(anyhow::Error::msg("Invalid key derivation parameters"))
+        };
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        if scrypt::scrypt(psw.as_bytes(), salt, &params, &mut key[..]).is_err(){
+            return Err(anyhow::Error::msg("Error during key derivation"));
+        }
+        Ok(StateKey{
+            key,
+            salt: *salt,
+            log_n,
+        })
+    }
+
+    ///
+    /// Check if the key can decrypt an envelope, that is the envelope was encrypted with the same salt
+    ///
+    pub(crate) fn matches(&self, input: &[u8]) -> bool{
+        match decode_config(input, URL_SAFE_NO_PAD){
+            Ok(bytes) => parse_header(&bytes) == Some((self.salt, self.log_n)),
+            Err(_) => false
+        }
+    }
+
+    pub(crate) fn encrypt(&self, bytes: &[u8], err_msg: &'static str) -> Result<Vec<u8>>{
+        let mut header = ENVELOPE_MAGIC.to_vec();
+        header.push(ENCRYPTION_FORMAT_VERSION);
+        header.push(self.log_n);
+        header.extend_from_slice(&self.salt);
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let chacha = XChaCha20Poly1305::new(GenericArray::from_slice(&self.key[..]));
+        let enc = match chacha.encrypt(GenericArray::from_slice(&nonce), Payload{ msg: bytes, aad: &header }){
+            Ok(res) => res,
+            Err(_) => return Err(anyhow::Error::msg(err_msg)),
+        };
+
+        let mut envelope = header;
+        envelope.extend_from_slice(&nonce);
+        envelope.extend(enc);
+        Ok(encode_config(&envelope, URL_SAFE_NO_PAD).as_bytes().to_vec())
+    }
+
+    pub(crate) fn decrypt(&self, input: &[u8], err_msg: &'static str) -> Result<Zeroizing<Vec<u8>>>{
+        let bytes = decode_config(input, URL_SAFE_NO_PAD)?;
+        if parse_header(&bytes) != Some((self.salt, self.log_n)) || bytes.len() < HEADER_LEN + NONCE_LEN{
+            return Err(anyhow::Error::msg(err_msg));
+        }
+
+        let (header, body) = bytes.split_at(HEADER_LEN);
+        let (nonce, enc) = body.split_at(NONCE_LEN);
+        let chacha = XChaCha20Poly1305::new(GenericArray::from_slice(&self.key[..]));
+        match chacha.decrypt(GenericArray::from_slice(nonce), Payload{ msg: enc, aad: header }){
+            Ok(res) => Ok(Zeroizing::new(res)),
+            Err(_) => Err(anyhow::Error::msg(err_msg)),
+        }
+    }
+}
+
+///
+/// Encrypt the data with a key derived from the password with a new random salt
+///
+pub(crate) fn encrypt_bytes(bytes: &[u8], psw: &str, err_msg: &'static str) -> Result<Vec<u8>>{
+    StateKey::generate(psw)?.encrypt(bytes, err_msg)
+}
+
+///
+/// Decrypt an envelope created by `encrypt_bytes`, or the data encrypted by the previous versions of the lib
+///
+pub(crate) fn decrypt_bytes(input: &[u8], psw: &str, err_msg: &'static str) -> Result<Zeroizing<Vec<u8>>>{
+    let bytes = decode_config(input, URL_SAFE_NO_PAD)?;
+    if parse_header(&bytes).is_none(){
+        return decrypt_legacy(&bytes, psw, err_msg);
+    }
+    StateKey::for_envelope(input, psw)?.decrypt(input, err_msg)
+}
+
+fn parse_header(bytes: &[u8]) -> Option<([u8; SALT_LEN], u8)>{
+    if bytes.len() < HEADER_LEN + NONCE_LEN || !bytes.starts_with(ENVELOPE_MAGIC) || bytes[4] != ENCRYPTION_FORMAT_VERSION{
+        return None;
+    }
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&bytes[6..HEADER_LEN]);
+    Some((salt, bytes[5]))
+}
+
+///
+/// Decrypt the data encrypted with the key and the nonce derived only from the password (version 0).
+/// They are only read: the data is always encrypted again with `encrypt_bytes`
+///
+fn decrypt_legacy(bytes: &[u8], psw: &str, err_msg: &'static str) -> Result<Zeroizing<Vec<u8>>>{
+    let (key, nonce) = legacy_key_nonce(psw);
+    let chacha = XChaCha20Poly1305::new(GenericArray::from_slice(&key[..]));
+    match chacha.decrypt(GenericArray::from_slice(&nonce[..]), bytes){
+        Ok(res) => Ok(Zeroizing::new(res)),
+        Err(_) => Err(anyhow::Error::msg(err_msg)),
+    }
+}
+
+fn legacy_key_nonce(psw: &str) -> (Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>) {
+    let psw_hash = Zeroizing::new(hash_string(psw));
+    let key_hash = &psw_hash[..32];
+    let nonce_hash = Zeroizing::new(hash_string(key_hash));
+    let key = key_hash.as_bytes();
+    let nonce = nonce_hash[..24].as_bytes();
+    (Zeroizing::new(key.to_vec()), Zeroizing::new(nonce.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy_encrypt(bytes: &[u8], psw: &str) -> Vec<u8>{
+        let (key, nonce) = legacy_key_nonce(psw);
+        let chacha = XChaCha20Poly1305::new(GenericArray::from_slice(&key[..]));
+        let enc = chacha.encrypt(GenericArray::from_slice(&nonce[..]), bytes).unwrap();
+        encode_config(&enc, URL_SAFE_NO_PAD).as_bytes().to_vec()
+    }
+
+    #[test]
+    fn same_data_and_password_give_different_envelopes() {
+        let first = encrypt_bytes(b"author state", "psw", "err").unwrap();
+        let second = encrypt_bytes(b"author state", "psw", "err").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(&decrypt_bytes(&first, "psw", "err").unwrap()[..], b"author state");
+        assert_eq!(&decrypt_bytes(&second, "psw", "err").unwrap()[..], b"author state");
+    }
+
+    #[test]
+    fn same_key_never_reuses_the_nonce() {
+        let key = StateKey::generate("psw").unwrap();
+        let first = decode_config(key.encrypt(b"data", "err").unwrap(), URL_SAFE_NO_PAD).unwrap();
+        let second = decode_config(key.encrypt(b"data", "err").unwrap(), URL_SAFE_NO_PAD).unwrap();
+        assert_eq!(first[..HEADER_LEN], second[..HEADER_LEN]);
+        assert_ne!(first[HEADER_LEN..HEADER_LEN + NONCE_LEN], second[HEADER_LEN..HEADER_LEN + NONCE_LEN]);
+    }
+
+    #[test]
+    fn wrong_password_or_tampered_header_fail() {
+        let enc = encrypt_bytes(b"author state", "psw", "err").unwrap();
+        assert!(decrypt_bytes(&enc, "other", "err").is_err());
+
+        let mut bytes = decode_config(&enc, URL_SAFE_NO_PAD).unwrap();
+        bytes[7] ^= 1;
+        let tampered = encode_config(&bytes, URL_SAFE_NO_PAD);
+        assert!(decrypt_bytes(tampered.as_bytes(), "psw", "err").is_err());
+    }
+
+    #[test]
+    fn legacy_data_is_still_readable() {
+        let enc = legacy_encrypt(b"old state", "psw");
+        assert_eq!(&decrypt_bytes(&enc, "psw", "err").unwrap()[..], b"old state");
+    }
+
+    #[test]
+    fn key_decrypts_only_its_envelopes() {
+        let key = StateKey::generate("psw").unwrap();
+        let own = key.encrypt(b"data", "err").unwrap();
+        let other = encrypt_bytes(b"data", "psw", "err").unwrap();
+        assert!(key.matches(&own));
+        assert!(!key.matches(&other));
+        assert!(key.decrypt(&other, "err").is_err());
+        assert_eq!(&StateKey::for_envelope(&other, "psw").unwrap().decrypt(&other, "err").unwrap()[..], b"data");
+    }
+}
@@ -6,24 +6,47 @@ use iota_streams::{
     app_channels::api::tangle::Subscriber
 };
 use iota_streams::app::message::HasLink;
-use iota_streams::app_channels::api::tangle::MessageContent;
+use iota_streams::app_channels::api::tangle::{MessageContent, UnwrappedMessage};
 
 use crate::payload::payload_types::{StreamsPacket, StreamsPacketSerializer};
-use crate::utility::iota_utility::{create_link, msg_index, hash_string};
-use crate::payload::payload_serializers::RawPacket;
-use crate::channels::channel_state::ChannelState;
+use crate::utility::iota_utility::{create_link, msg_index, hash_string, random_seed, public_key_hex, public_key_hex_matches, public_key_matches};
+use iota_streams::core_edsig::signature::ed25519::PublicKey;
+use crate::channels::channel_state::{ChannelState, current_timestamp, parse_state_msg, state_msg_tag};
+use crate::channels::{ChainIssue, ChainReport, ChannelMirror, ChannelWriter, CheckedMsg, MessageFilter, MessageProof, ProofMsg};
+use std::collections::{HashMap, HashSet};
+use iota_streams::app::transport::Transport;
+use crate::channels::received_msg::ReceivedMsg;
+use crate::channels::msg_cache::MsgCache;
+use serde::de::DeserializeOwned;
 use iota_streams::app::transport::tangle::client::SendOptions;
-use crate::user_builders::subscriber_builder::SubscriberBuilder;
 use crate::channels::builders::channel_builders::ChannelReaderBuilder;
+use crate::utility::retry_policy::{RetryPolicy, with_retry};
+use crate::utility::secrets::{SecretString, SymmetricKey};
+use crate::storage::state_store::StateStore;
+use zeroize::Zeroizing;
+use crate::user_builders::node_pool::{NodePool, with_failover};
+use crate::user_builders::network::Network;
+use crate::transport::channel_transport::{ChannelTransport, announcement_reachable};
 
 ///
 /// Channel Reader
 ///
-pub struct ChannelReader {
-    subscriber: Subscriber<StreamsClient>,
+pub struct ChannelReader<Trans: ChannelTransport = StreamsClient> {
+    subscriber: Subscriber<Trans>,
+    transport: Option<Trans>,
     channel_address: String,
     announcement_id: String,
-    unread_msgs: Vec<(String, Vec<u8>, Vec<u8>)>,
+    unread_msgs: Vec<ReceivedMsg>,
+    retry_policy: RetryPolicy,
+    node_pool: Option<NodePool>,
+    created_at: u64,
+    subscription_id: Option<String>,
+    read_cursor: Option<String>,
+    pinned_author: Option<String>,
+    replayer: Option<Subscriber<Trans>>,
+    msg_cache: MsgCache,
+    mirror: Option<ChannelMirror>,
+    unmirrored: Vec<ReceivedMsg>,
 }
 
 impl ChannelReader {
@@ -35,61 +58,227 @@ impl ChannelReader {
         ChannelReaderBuilder::new()
     }
 
+    ///
+    /// Restore the channels from a previously stored byte array state
+    ///
+    pub async fn import_from_bytes(state: &[u8], psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
+        let psw = psw.into();
+        let channel_state = ChannelState::decrypt(&state, psw.expose())?;
+        let channel = ChannelReader::import(&channel_state, psw.expose(), node_url, send_options).await?;
+        Ok(channel)
+    }
+
+    ///
+    /// Restore the channels from a previously stored state in a file
+    ///
+    pub async fn import_from_file(file_path: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
+        let psw = psw.into();
+        let channel_state = ChannelState::from_file(file_path, psw.expose())?;
+        ChannelReader::import(&channel_state, psw.expose(), node_url, send_options).await
+    }
+
+    ///
+    /// Restore the channels from a state file or, if it is missing or corrupted, from its most recent readable backup.
+    ///
+    /// # Return Value
+    /// It returns the channel and the backup generation the state was loaded from, 0 if it is the file itself
+    ///
+    pub async fn import_from_file_or_backup(file_path: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<(ChannelReader, usize)>{
+        let psw = psw.into();
+        let (channel_state, generation) = ChannelState::from_file_or_backup(file_path, psw.expose())?;
+        let channel = ChannelReader::import(&channel_state, psw.expose(), node_url, send_options).await?;
+        Ok((channel, generation))
+    }
+
+    ///
+    /// Restore the channels from the most recent backup published by `backup_to_tangle` in the backup channel.
+    /// The backup channel is read from the nodes of the specified network, or from the specified node.
+    /// Node errors are retried with the specified RetryPolicy, or the default one if None, that is set on the restored reader too
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_from_tangle(backup_id: &str, backup_announce_id: &str, channel_id: &str, announce_id: &str, state_psw: impl Into<SecretString>, network: &Network, node_url: Option<&str>, send_options: Option<SendOptions>, retry_policy: Option<RetryPolicy>) -> Result<ChannelReader>{
+        let state_psw = state_psw.into();
+        let retry_policy = retry_policy.unwrap_or_default();
+        let node_pool = NodePool::from_network(network).override_with(node_url, send_options.clone());
+        let state = match ChannelWriter::find_state(backup_id, backup_announce_id, (channel_id, announce_id), None, &node_pool, &retry_policy).await?{
+            Some(state) => state,
+            None => return Err(anyhow::Error::msg("There is no backup of the reader in the channels"))
+        };
+        let mut channel = ChannelReader::import_from_bytes(&state, state_psw.expose(), node_url, send_options).await?;
+        channel.set_retry_policy(retry_policy);
+        Ok(channel)
+    }
+
+    ///
+    /// Restore the channels from the specified backup message published by `backup_to_tangle`, without walking the backup channel.
+    /// The backup channel is read from the nodes of the specified network, or from the specified node.
+    /// Node errors are retried with the specified RetryPolicy, or the default one if None, that is set on the restored reader too
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_from_backup(backup_id: &str, backup_announce_id: &str, backup_msg_id: &str, channel_id: &str, announce_id: &str, state_psw: impl Into<SecretString>, network: &Network, node_url: Option<&str>, send_options: Option<SendOptions>, retry_policy: Option<RetryPolicy>) -> Result<ChannelReader>{
+        let state_psw = state_psw.into();
+        let retry_policy = retry_policy.unwrap_or_default();
+        let node_pool = NodePool::from_network(network).override_with(node_url, send_options.clone());
+        let state = ChannelWriter::find_state(backup_id, backup_announce_id, (channel_id, announce_id), Some(backup_msg_id), &node_pool, &retry_policy).await?
+            .ok_or_else(|| anyhow::Error::msg("There is no backup of the reader in the channels"))?;
+        let mut channel = ChannelReader::import_from_bytes(&state, state_psw.expose(), node_url, send_options).await?;
+        channel.set_retry_policy(retry_policy);
+        Ok(channel)
+    }
+
+    ///
+    /// Restore the channels from the state saved in a StateStore with the specified channel id
+    ///
+    pub async fn load_from(store: &dyn StateStore, channel_id: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
+        let psw = psw.into();
+        let state = store.load(channel_id)?;
+        ChannelReader::import_from_bytes(&state, psw.expose(), node_url, send_options).await
+    }
+}
+
+impl<Trans: ChannelTransport> ChannelReader<Trans> {
+
     ///
     /// Initialize the Channel Reader
     ///
-    pub fn new(subscriber: Subscriber<StreamsClient>, channel_address: &str, announcement_id: &str) -> ChannelReader {
+    pub fn new(subscriber: Subscriber<Trans>, channel_address: &str, announcement_id: &str) -> ChannelReader<Trans> {
         ChannelReader {
             subscriber,
+            transport: None,
             channel_address: channel_address.to_string(),
             announcement_id: announcement_id.to_string(),
-            unread_msgs: Vec::new()
+            unread_msgs: Vec::new(),
+            retry_policy: RetryPolicy::default(),
+            node_pool: None,
+            created_at: current_timestamp(),
+            subscription_id: None,
+            read_cursor: None,
+            pinned_author: None,
+            replayer: None,
+            msg_cache: MsgCache::new(),
+            mirror: None,
+            unmirrored: Vec::new(),
         }
     }
 
     ///
-    /// Restore the channels from a previously stored byte array state
+    /// Restore the channels from a state with the specified transport, for example a MemoryTransport.
+    /// The reader doesn't switch node on errors, since it has no nodes
     ///
-    pub async fn import_from_bytes(state: &[u8], psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
-        let channel_state = ChannelState::decrypt(&state, &psw)?;
-        let channel = ChannelReader::import(&channel_state, psw, node_url, send_options).await?;
-        Ok(channel)
+    pub async fn import_with_transport(channel_state: &ChannelState, psw: impl Into<SecretString>, transport: Trans) -> Result<ChannelReader<Trans>>{
+        let psw = psw.into();
+        ChannelReader::from_state(channel_state, psw.expose(), transport, None).await
     }
 
     ///
     /// Export the channels state into an encrypted byte array.
     ///
-    pub async fn export_to_bytes(&self, psw: &str)-> Result<Vec<u8>>{
-        let channel_state = self.export(psw).await?;
-        channel_state.encrypt(psw)
+    pub async fn export_to_bytes(&self, psw: impl Into<SecretString>)-> Result<Vec<u8>>{
+        let psw = psw.into();
+        let channel_state = self.export(psw.expose()).await?;
+        channel_state.encrypt(psw.expose())
     }
 
     ///
-    /// Attach the Reader to Channel
+    /// Save the encrypted channels state in a StateStore, using the channel address as id
     ///
-    pub async fn attach(&mut self) -> Result<()> {
-        let link = create_link(&self.channel_address, &self.announcement_id)?;
-        self.subscriber.receive_announcement(&link).await?;
+    pub async fn persist_to(&self, store: &dyn StateStore, psw: impl Into<SecretString>) -> Result<()>{
+        let psw = psw.into();
+        let state = self.export_to_bytes(psw.expose()).await?;
+        store.save(&self.channel_address, &state)
+    }
 
-        if !self.fetch_next_msgs().await{
-            return Ok(());
-        }
+    ///
+    /// Stores the channels state in a file. The subscriber state is encrypted with the specified password
+    ///
+    pub async fn export_to_file(&self, psw: impl Into<SecretString>, file_path: &str)-> Result<()>{
+        let psw = psw.into();
+        let channel_state = self.export(psw.expose()).await?;
+        channel_state.write_to_file(file_path, psw.expose())
+    }
 
-        let comp = format!("{}:{}.state", self.channel_address, self.announcement_id);
-        let msg = &self.unread_msgs[0];
-        let packet = match RawPacket::from_streams_response(&msg.1, &msg.2, &None){
-            Ok(packet) => packet,
-            Err(_) => return Ok(())
-        };
+    ///
+    /// Stores the channels state in a file, keeping up to `backups` generations of the previous content
+    ///
+    pub async fn export_to_file_with_backups(&self, psw: impl Into<SecretString>, file_path: &str, backups: usize)-> Result<()>{
+        let psw = psw.into();
+        let channel_state = self.export(psw.expose()).await?;
+        channel_state.write_to_file_with_backups(file_path, psw.expose(), backups)
+    }
 
-        match packet.deserialize_public::<String>(){
-            Ok(state_msg) => {
-                if state_msg == comp{
-                    self.unread_msgs.remove(0);
-                }
+    ///
+    /// Publish the encrypted state of the reader in a backup channel owned by the application.
+    /// The backup can be restored with `import_from_tangle` or, without walking the backup channel, with `import_from_backup`
+    ///
+    /// # Return Value
+    /// It returns the id of the backup message
+    ///
+    pub async fn backup_to_tangle<BackupTrans: ChannelTransport>(&self, backup: &mut ChannelWriter<BackupTrans>, state_psw: impl Into<SecretString>) -> Result<String>{
+        let state_psw = state_psw.into();
+        let tag = state_msg_tag(&self.channel_address, &self.announcement_id);
+        let state = self.export_to_bytes(state_psw.expose()).await?;
+        backup.publish_tagged_state(&tag, &state).await
+    }
+
+    ///
+    /// Accept only the announcement and the packets signed by the author with the specified
+    /// hex encoded public key or fingerprint
+    ///
+    pub fn pin_author(&mut self, public_key: &str){
+        self.pinned_author = Some(public_key.to_string());
+    }
+
+    ///
+    /// Mirror every fetched message into the store of the mirror
+    ///
+    pub fn set_mirror(&mut self, mirror: ChannelMirror){
+        self.mirror = Some(mirror);
+    }
+
+    ///
+    /// Set the policy used to retry the operations that fail because of transient node errors
+    ///
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy){
+        self.retry_policy = retry_policy;
+    }
+
+    ///
+    /// Set the nodes used to switch node when the current one stops working
+    ///
+    pub fn set_node_pool(&mut self, node_pool: NodePool){
+        self.node_pool = Some(node_pool);
+    }
+
+    ///
+    /// Get the url of the node currently used by the reader
+    ///
+    pub fn current_node(&self) -> Option<String>{
+        self.node_pool.as_ref().map(|pool| pool.current().to_string())
+    }
+
+    ///
+    /// Get the network of the nodes used by the channel
+    ///
+    pub fn network(&self) -> Network{
+        match &self.node_pool{
+            None => Network::default(),
+            Some(pool) => pool.network()
+        }
+    }
+
+    ///
+    /// Attach the Reader to Channel
+    ///
+    pub async fn attach(&mut self) -> Result<()> {
+        let link = create_link(&self.channel_address, &self.announcement_id)?;
+        with_failover!(self, self.subscriber.receive_announcement(&link))?;
+        if let Some(pinned) = &self.pinned_author{
+            match self.subscriber.author_public_key(){
+                Some(pk) if public_key_matches(pk, pinned) => {},
+                _ => return Err(anyhow::Error::msg("The announcement is not signed by the pinned author"))
             }
-            Err(_) => {}
-        };
+        }
+        self.fetch_next_msgs().await;
         Ok(())
     }
 
@@ -98,22 +287,20 @@ impl ChannelReader {
     ///
     pub async fn send_subscription(&mut self) -> Result<String>{
         let link = create_link(&self.channel_address, &self.announcement_id)?;
-        let addr = self.subscriber.send_subscribe(&link).await?.msgid.to_string();
+        let addr = with_failover!(self, self.subscriber.send_subscribe(&link))?.msgid.to_string();
+        self.subscription_id = Some(addr.clone());
         Ok(addr)
     }
 
     ///
     /// Receive a signed packet and return it in a StreamsPacket struct that is able to parse its content to your own types
     ///
-    pub async fn receive_parsed_packet<T>(&mut self, msg_id: &str, key_nonce: Option<([u8;32], [u8;24])>) -> Result<StreamsPacket<T>>
+    pub async fn receive_parsed_packet<T>(&mut self, msg_id: &str, key: Option<&SymmetricKey>) -> Result<StreamsPacket<T>>
         where
             T: StreamsPacketSerializer,
     {
-        let msg_link = create_link(&self.channel_address, msg_id)?;
-        let (_, public_payload, masked_payload) = self.subscriber.receive_signed_packet(&msg_link).await?;
-        let (p_data, m_data) = (&public_payload.0, &masked_payload.0);
-
-        StreamsPacket::from_streams_response(&p_data, &m_data, &key_nonce)
+        let (p_data, m_data) = self.receive_packet(msg_id).await?;
+        StreamsPacket::from_streams_response(&p_data, &m_data, key)
     }
 
     ///
@@ -123,9 +310,236 @@ impl ChannelReader {
         where
             T: StreamsPacketSerializer,
     {
-        let msg_link = create_link(&self.channel_address, msg_id)?;
-        let (_, public_payload, masked_payload) = self.subscriber.receive_signed_packet(&msg_link).await?;
-        Ok((public_payload.0.clone(), masked_payload.0.clone()))
+        self.receive_packet(msg_id).await
+    }
+
+    ///
+    /// Walk the chain again from the announcement and get all its messages.
+    /// The read cursor is reset to the last message of the channel: the unread messages are returned with the others
+    /// and the fetch methods return only the messages attached after the replay.
+    /// The messages are kept in a local cache, so only the messages attached after the previous replay are fetched from the node
+    ///
+    /// # Return Value
+    /// It returns a Vector of Tuple containing (msg_id, public_bytes, masked_bytes)
+    ///
+    pub async fn replay_from_start(&mut self) -> Result<Vec<(String, Vec<u8>, Vec<u8>)>>{
+        self.fetch_next_msgs().await;
+        self.replay().await?;
+        self.reset_cursor();
+        Ok(self.msg_cache.all().iter().map(|msg| msg.to_raw()).collect())
+    }
+
+    ///
+    /// Same as `replay_from_start`, but it returns the messages starting from the specified one, included
+    ///
+    pub async fn replay_from(&mut self, msg_id: &str) -> Result<Vec<(String, Vec<u8>, Vec<u8>)>>{
+        self.fetch_next_msgs().await;
+        self.replay().await?;
+        let msgs = match self.msg_cache.from_msg(msg_id){
+            Some(msgs) => msgs.iter().map(|msg| msg.to_raw()).collect(),
+            None => return Err(anyhow::Error::msg(format!("There is no message {} in the channels", msg_id)))
+        };
+        self.reset_cursor();
+        Ok(msgs)
+    }
+
+    ///
+    /// Get `limit` messages of the channel starting from the `offset` position, walking the chain like `replay_from_start`.
+    /// The read cursor is not moved, since a page doesn't deliver all the messages. Only the page is copied from the local cache
+    ///
+    /// # Return Value
+    /// It returns the page and the total number of messages of the channel
+    ///
+    pub async fn replay_page(&mut self, offset: usize, limit: usize) -> Result<(Vec<(String, Vec<u8>, Vec<u8>)>, usize)>{
+        self.replay().await?;
+        let page = self.msg_cache.all().iter()
+            .skip(offset)
+            .take(limit)
+            .map(|msg| msg.to_raw())
+            .collect();
+        Ok((page, self.msg_cache.len()))
+    }
+
+    ///
+    /// Walk the chain from the announcement with a dedicated subscriber, without moving the read cursor, and check it.
+    /// The signatures are verified while the messages are unwrapped: the report lists the messages that are signed
+    /// by other publishers than the author, the forks, the messages already seen by the reader that are no longer reachable
+    /// or that were seen in a different order, and the state messages that repeat a previous state.
+    /// The states are compared by their content, so they are checked only with the password used to publish them.
+    /// The messages of a single branch channel must be linked to the previous one, the ones of a multi branch channel to any message walked
+    ///
+    pub async fn verify_chain(&self, state_psw: Option<&str>) -> Result<ChainReport>{
+        let mut transport = self.walker_transport()?;
+        let mut walker = Subscriber::new(&random_seed(), transport.clone());
+        let link = create_link(&self.channel_address, &self.announcement_id)?;
+        with_retry!(self.retry_policy, walker.receive_announcement(&link))?;
+
+        let author = match walker.author_public_key(){
+            Some(pk) => public_key_hex(pk),
+            None => return Err(anyhow::Error::msg("The announcement has no author key"))
+        };
+        let mut issues = vec![];
+        if let Some(pinned) = &self.pinned_author{
+            if !public_key_hex_matches(&author, pinned){
+                issues.push(ChainIssue::PinnedAuthorMismatch{ author: author.clone() });
+            }
+        }
+
+        let mut msgs = vec![];
+        let mut states: HashMap<String, String> = HashMap::new();
+        let linear = !walker.is_multi_branching();
+        // Every message of the chain must be linked to the previous one, or at least to a message already walked
+        let mut reached: HashSet<String> = HashSet::new();
+        reached.insert(self.announcement_id.clone());
+        let mut last_id = self.announcement_id.clone();
+        for msg in walker.fetch_all_next_msgs().await{
+            let msg_link = msg.link.clone();
+            let msg_id = msg_link.msgid.to_string();
+            let prev_msg_id = msg.prev_link.rel().to_string();
+            if !reached.contains(&prev_msg_id){
+                issues.push(ChainIssue::BrokenLink{ msg_id: msg_id.clone(), prev_msg_id: prev_msg_id.clone() });
+            } else if linear && prev_msg_id != last_id{
+                issues.push(ChainIssue::Discontinuity{ msg_id: msg_id.clone(), expected_prev_msg_id: last_id.clone(), prev_msg_id: prev_msg_id.clone() });
+            }
+            reached.insert(msg_id.clone());
+            last_id = msg_id.clone();
+
+            let (publisher, is_state) = match msg.body{
+                MessageContent::SignedPacket {pk, public_payload, masked_payload } => {
+                    let state = parse_state_msg(&self.channel_address, &self.announcement_id, &public_payload.0, &masked_payload.0);
+                    // Two states are the same if they restore the channel at the same message
+                    let content = match (&state, state_psw){
+                        (Some(state), Some(psw)) => ChannelState::decrypt(state, psw).ok().map(|state| state.last_msg_id()),
+                        _ => None
+                    };
+                    if let Some(content) = content{
+                        match states.get(&content){
+                            Some(previous) => issues.push(ChainIssue::DuplicateState{ msg_id: msg_id.clone(), previous_msg_id: previous.clone() }),
+                            None => { states.insert(content, msg_id.clone()); }
+                        }
+                    }
+                    (public_key_hex(&pk), state.is_some())
+                }
+                _ => continue
+            };
+
+            if publisher != author{
+                issues.push(ChainIssue::ForeignPublisher{ msg_id: msg_id.clone(), publisher: publisher.clone() });
+            }
+            match transport.recv_messages(&msg_link).await{
+                Ok(copies) if copies.len() > 1 => issues.push(ChainIssue::Fork{ msg_id: msg_id.clone(), copies: copies.len() }),
+                Ok(_) => {},
+                Err(e) => issues.push(ChainIssue::Unchecked{ msg_id: msg_id.clone(), error: e.to_string() })
+            }
+
+            let seq = msgs.len();
+            msgs.push(CheckedMsg{ msg_id, seq, publisher, is_state, prev_msg_id });
+        }
+
+        // The messages seen by the reader, and the ones they are linked to, must still be in the chain
+        let walked: HashMap<&str, usize> = msgs.iter().map(|msg| (msg.msg_id.as_str(), msg.seq)).collect();
+        let mut missing: Vec<String> = vec![];
+        let mut last_seq: Option<usize> = None;
+        for cached in self.msg_cache.all(){
+            if let Some(seq) = walked.get(cached.msg_id.as_str()){
+                if let Some(last) = last_seq{
+                    if *seq <= last{
+                        issues.push(ChainIssue::OutOfOrder{ msg_id: cached.msg_id.clone(), expected_seq: last + 1, found_seq: *seq });
+                    }
+                }
+                last_seq = Some(*seq);
+            }
+        }
+        for seen in self.msg_cache.all().iter().chain(self.unread_msgs.iter()){
+            if !walked.contains_key(seen.msg_id.as_str()){
+                missing.push(seen.msg_id.clone());
+            }
+            if let Some(prev) = &seen.prev_msg_id{
+                if !reached.contains(prev){
+                    missing.push(prev.clone());
+                }
+            }
+        }
+        if let Some(cursor) = &self.read_cursor{
+            if !walked.contains_key(cursor.as_str()){
+                missing.push(cursor.clone());
+            }
+        }
+        let mut reported = HashSet::new();
+        for msg_id in missing{
+            if reported.insert(msg_id.clone()){
+                issues.push(ChainIssue::Gap{ msg_id });
+            }
+        }
+
+        Ok(ChainReport{
+            channel_address: self.channel_address.clone(),
+            announcement_id: self.announcement_id.clone(),
+            author_public_key: author,
+            checked_at: current_timestamp(),
+            msgs,
+            issues,
+        })
+    }
+
+    ///
+    /// Export a self-contained proof that the specified message was published in the channel.
+    /// The proof contains the binary messages from the announcement to the message, so `verify_proof` needs no node
+    ///
+    pub async fn export_proof(&mut self, msg_id: &str) -> Result<MessageProof>{
+        let mut transport = self.walker_transport()?;
+        let mut walker = Subscriber::new(&random_seed(), transport.clone());
+        let announce_link = create_link(&self.channel_address, &self.announcement_id)?;
+        with_retry!(self.retry_policy, walker.receive_announcement(&announce_link))?;
+
+        let mut links = vec![announce_link];
+        let mut proved = None;
+        for msg in walker.fetch_all_next_msgs().await{
+            links.push(msg.link.clone());
+            if msg.link.msgid.to_string() != msg_id{
+                continue;
+            }
+            if let MessageContent::SignedPacket {pk, public_payload, masked_payload } = msg.body{
+                proved = Some((public_key_hex(&pk), public_payload.0, masked_payload.0));
+            }
+            break;
+        }
+        let (publisher_key, public_payload, masked_payload) = match proved{
+            Some(proved) => proved,
+            None => return Err(anyhow::Error::msg(format!("There is no signed packet {} in the channels", msg_id)))
+        };
+
+        let mut messages = vec![];
+        for link in &links{
+            let msg = with_retry!(self.retry_policy, transport.recv_message(link))?;
+            messages.push(ProofMsg::from_message(&msg));
+        }
+
+        Ok(MessageProof{
+            channel_address: self.channel_address.clone(),
+            announcement_id: self.announcement_id.clone(),
+            msg_id: msg_id.to_string(),
+            publisher_key,
+            links: links.iter().skip(1).map(|link| link.msgid.to_string()).collect(),
+            public_payload,
+            masked_payload,
+            messages,
+        })
+    }
+
+    ///
+    /// Get the number of messages in the local cache
+    ///
+    pub fn cached_msgs(&self) -> usize{
+        self.msg_cache.len()
+    }
+
+    ///
+    /// Remove all the messages from the local cache: the next replay walks the chain from the node again
+    ///
+    pub fn clear_cache(&mut self){
+        self.msg_cache.clear();
+        self.replayer = None;
     }
 
     ///
@@ -136,9 +550,7 @@ impl ChannelReader {
     ///
     pub async fn fetch_raw_msgs(&mut self) -> Vec<(String, Vec<u8>, Vec<u8>)> {
         self.fetch_next_msgs().await;
-        let res = self.unread_msgs.clone();
-        self.unread_msgs = Vec::new();
-        res
+        self.take_unread_msgs().iter().map(|msg| msg.to_raw()).collect()
     }
 
     ///
@@ -147,18 +559,74 @@ impl ChannelReader {
     /// # Return Value
     /// It returns a Vector of StreamsPacket that can parse its content
     ///
-    pub async fn fetch_parsed_msgs<T>(&mut self, key_nonce: &Option<([u8;32], [u8;24])>) -> Result<Vec<(String, StreamsPacket<T>)>>
+    pub async fn fetch_parsed_msgs<T>(&mut self, key: Option<&SymmetricKey>) -> Result<Vec<(String, StreamsPacket<T>)>>
     where
         T: StreamsPacketSerializer
     {
         self.fetch_next_msgs().await;
 
         let mut res = vec![];
-        for (id, p, m) in &self.unread_msgs {
-            res.push((id.clone(), StreamsPacket::from_streams_response(p, m, key_nonce)?));
+        for msg in &self.unread_msgs {
+            res.push((msg.msg_id.clone(), StreamsPacket::from_streams_response(&msg.public, &msg.masked, key)?));
         }
 
-        self.unread_msgs = Vec::new();
+        self.take_unread_msgs();
+        Ok(res)
+    }
+
+    ///
+    /// Fetch all the remaining msgs and return only the ones selected by the filter.
+    /// The public data of the packets is deserialized as `P` and passed to the filter, the packets that can't be deserialized are skipped.
+    /// When the first message of the range has already been read, the range starts from the local cache, updated by walking the chain.
+    /// The read cursor is moved to the end of the range: the unread messages scanned and not selected are read too
+    ///
+    /// # Return Value
+    /// It returns a Vector of StreamsPacket that can parse its content
+    ///
+    pub async fn fetch_filtered<T, P>(&mut self, key: Option<&SymmetricKey>, filter: &MessageFilter<P>) -> Result<Vec<(String, StreamsPacket<T>)>>
+    where
+        T: StreamsPacketSerializer,
+        P: DeserializeOwned,
+    {
+        self.fetch_next_msgs().await;
+
+        let mut scanned: Vec<ReceivedMsg> = vec![];
+        if let Some(from_msg) = filter.from_msg_id(){
+            if !self.unread_msgs.iter().any(|msg| msg.msg_id == from_msg){
+                self.replay().await?;
+                let unread: HashSet<&str> = self.unread_msgs.iter().map(|msg| msg.msg_id.as_str()).collect();
+                match self.msg_cache.from_msg(from_msg){
+                    Some(msgs) => scanned.extend(msgs.iter().filter(|msg| !unread.contains(msg.msg_id.as_str())).cloned()),
+                    None => return Err(anyhow::Error::msg(format!("There is no message {} in the channels", from_msg)))
+                }
+            }
+        }
+        let read_msgs = scanned.len();
+        scanned.extend(self.unread_msgs.iter().cloned());
+
+        let range = filter.range(&scanned);
+        let mut res = vec![];
+        for msg in &scanned[range.clone()] {
+            let packet = StreamsPacket::<T>::from_streams_response(&msg.public, &msg.masked, key)?;
+            let public = match packet.deserialize_public::<P>(){
+                Ok(public) => public,
+                Err(_) => continue
+            };
+            let timestamp = match filter.has_time_range(){
+                true => Some(self.msg_timestamp(&msg.msg_id).await?),
+                false => None
+            };
+            if filter.matches(msg, &public, timestamp){
+                res.push((msg.msg_id.clone(), packet));
+            }
+        }
+
+        if range.end > read_msgs{
+            let read: Vec<ReceivedMsg> = self.unread_msgs.drain(..range.end - read_msgs).collect();
+            if let Some(msg) = read.last(){
+                self.read_cursor = Some(msg.msg_id.clone());
+            }
+        }
         Ok(res)
     }
 
@@ -169,6 +637,20 @@ impl ChannelReader {
         (self.channel_address.clone(), self.announcement_id.clone())
     }
 
+    ///
+    /// Get the id of the subscription message sent by the reader
+    ///
+    pub fn subscription_id(&self) -> Option<String>{
+        self.subscription_id.clone()
+    }
+
+    ///
+    /// Get the id of the last message returned by the fetch methods
+    ///
+    pub fn read_cursor(&self) -> Option<String>{
+        self.read_cursor.clone()
+    }
+
     ///
     /// Get the index of msg to find the transaction on the tangle
     ///
@@ -178,49 +660,469 @@ impl ChannelReader {
     }
 }
 
-impl ChannelReader{
+impl<Trans: ChannelTransport> ChannelReader<Trans>{
 
-    async fn import(channel_state: &ChannelState, psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
-        let subscriber = SubscriberBuilder::build_from_state(
-            &channel_state.user_state(),
-            psw,
-            node_url,
-            send_options
-        ).await?;
+    pub(crate) async fn from_state(channel_state: &ChannelState, psw: &str, transport: Trans, node_pool: Option<NodePool>) -> Result<ChannelReader<Trans>>{
+        let psw_hash = SecretString::from(hash_string(psw));
+        let subscriber = Subscriber::import(&channel_state.user_state(), psw_hash.expose(), transport.clone()).await?;
         let channel_address = subscriber.channel_address().unwrap().to_string();
+        // The announcement of a restored reader is not received again, so the pinned author is checked on the restored state
+        let pinned_author = channel_state.pinned_author();
+        if let (Some(pinned), Some(pk)) = (&pinned_author, subscriber.author_public_key()){
+            if !public_key_matches(pk, pinned){
+                return Err(anyhow::Error::msg("The channel of the state is not signed by the pinned author"));
+            }
+        }
 
         Ok(ChannelReader {
             subscriber,
+            transport: Some(transport),
             channel_address,
             announcement_id: channel_state.announcement_id(),
-            unread_msgs: Vec::new(),
+            unread_msgs: channel_state.unread_msgs(),
+            retry_policy: RetryPolicy::default(),
+            node_pool,
+            created_at: channel_state.created_at(),
+            subscription_id: channel_state.subscription_id(),
+            read_cursor: channel_state.read_cursor(),
+            pinned_author,
+            replayer: None,
+            msg_cache: MsgCache::new(),
+            mirror: None,
+            unmirrored: Vec::new(),
         })
     }
 
-    async fn export(&self, psw: &str) -> Result<ChannelState>{
-        let psw_hash = hash_string(psw);
-        let author_state = self.subscriber.export(&psw_hash).await?;
-        Ok(ChannelState::new(&author_state, &self.channel_address, &self.announcement_id, ""))
+    async fn failover(&mut self) -> Result<bool>{
+        let mut pool = match &self.node_pool{
+            None => return Ok(false),
+            Some(pool) => pool.clone()
+        };
+        if pool.next_healthy().await.is_none(){
+            return Ok(false);
+        }
+        self.connect_to(pool).await
+    }
+
+    ///
+    /// Go back to the primary node of the pool when it is healthy again, with the PrimaryBackup strategy
+    ///
+    /// # Return Value
+    /// It returns true if the subscriber moved to the primary node
+    ///
+    async fn restore_primary(&mut self) -> bool{
+        let mut pool = match &self.node_pool{
+            None => return false,
+            Some(pool) => pool.clone()
+        };
+        if !pool.restore_primary().await{
+            // Keep the time of the last check of the primary node
+            self.node_pool = Some(pool);
+            return false;
+        }
+        // If the subscriber can't be moved it stays on the current node, and the primary node is checked again with the next operation
+        self.connect_to(pool).await.unwrap_or(false)
+    }
+
+    ///
+    /// Move the subscriber to the current node of the pool, that replaces the pool of the channel
+    ///
+    async fn connect_to(&mut self, pool: NodePool) -> Result<bool>{
+        let transport = match Trans::connect(&pool){
+            None => return Ok(false),
+            Some(transport) => transport
+        };
+
+        let psw = SecretString::from(random_seed());
+        let subscriber_state = Zeroizing::new(self.subscriber.export(psw.expose()).await?);
+        self.subscriber = Subscriber::import(&subscriber_state, psw.expose(), transport.clone()).await?;
+        self.transport = Some(transport);
+        self.node_pool = Some(pool);
+        // The replay subscriber is created again on the new node with the next replay
+        self.replayer = None;
+        Ok(true)
+    }
+
+    pub(crate) fn set_transport(&mut self, transport: Trans){
+        self.transport = Some(transport);
+    }
+
+    ///
+    /// Get a transport for the subscribers that walk the chain without moving the reader
+    ///
+    fn walker_transport(&self) -> Result<Trans>{
+        if let Some(transport) = &self.transport{
+            return Ok(transport.clone());
+        }
+        match Trans::connect(&self.node_pool.clone().unwrap_or_default()){
+            Some(transport) => Ok(transport),
+            None => Err(anyhow::Error::msg("There is no transport to walk the channels"))
+        }
+    }
+
+    async fn is_reachable(&self) -> bool{
+        match self.walker_transport(){
+            Ok(transport) => announcement_reachable(transport, &self.channel_address, &self.announcement_id).await,
+            Err(_) => false
+        }
+    }
+
+    ///
+    /// Wait until the node is reachable again, following the retry policy, and then switch node
+    ///
+    /// # Return Value
+    /// It returns true if the reader can reach the tangle again
+    ///
+    async fn reconnect(&mut self) -> bool{
+        let err = anyhow::Error::msg("The node is not reachable");
+        let mut attempt = 0;
+        while let Some(delay) = self.retry_policy.next_delay(attempt, &err){
+            tokio::time::sleep(delay).await;
+            if self.is_reachable().await{
+                return true;
+            }
+            attempt += 1;
+        }
+        matches!(self.failover().await, Ok(true))
+    }
+
+    pub(crate) async fn export(&self, psw: &str) -> Result<ChannelState>{
+        let psw_hash = SecretString::from(hash_string(psw));
+        let author_state = Zeroizing::new(self.subscriber.export(psw_hash.expose()).await?);
+        let channel_state = ChannelState::new_reader(
+            &author_state,
+            &self.channel_address,
+            &self.announcement_id,
+            self.subscription_id.as_deref(),
+            self.read_cursor.as_deref(),
+            &self.network()
+        )
+            .with_created_at(self.created_at)
+            .with_pinned_author(self.pinned_author.as_deref())
+            .with_unread_msgs(self.unread_msgs.clone());
+        match &self.node_pool{
+            None => Ok(channel_state),
+            Some(node_pool) => Ok(channel_state.with_node_pool(node_pool))
+        }
+    }
+
+    fn check_publisher(&self, pk: &PublicKey) -> Result<()>{
+        self.check_publisher_hex(&public_key_hex(pk))
+    }
+
+    fn check_publisher_hex(&self, publisher: &str) -> Result<()>{
+        match &self.pinned_author{
+            Some(pinned) if !public_key_hex_matches(publisher, pinned) => Err(anyhow::Error::msg(format!("The packet is signed by {} and not by the pinned author", publisher))),
+            _ => Ok(())
+        }
+    }
+
+    ///
+    /// Get the timestamp recorded in the envelope of a message, in milliseconds from the unix epoch
+    ///
+    async fn msg_timestamp(&self, msg_id: &str) -> Result<u64>{
+        let link = create_link(&self.channel_address, msg_id)?;
+        let mut transport = self.walker_transport()?;
+        let msg = with_retry!(self.retry_policy, transport.recv_message(&link))?;
+        Ok(msg.timestamp)
+    }
+
+    ///
+    /// Get a packet from the local cache, or from the node if it is not cached
+    ///
+    async fn receive_packet(&mut self, msg_id: &str) -> Result<(Vec<u8>, Vec<u8>)>{
+        if let Some(msg) = self.msg_cache.get(msg_id){
+            self.check_publisher_hex(&msg.publisher)?;
+            return Ok((msg.public.clone(), msg.masked.clone()));
+        }
+
+        let msg_link = create_link(&self.channel_address, msg_id)?;
+        let (pk, public_payload, masked_payload) = with_failover!(self, self.subscriber.receive_signed_packet(&msg_link))?;
+        self.check_publisher(&pk)?;
+        Ok((public_payload.0.clone(), masked_payload.0.clone()))
+    }
+
+    ///
+    /// Bring the local cache up to date, using a dedicated subscriber that walks the chain from the announcement
+    ///
+    ///
+    /// Mark the messages fetched before a replay as read, since the replay has returned them
+    ///
+    fn reset_cursor(&mut self){
+        if let Some(msg) = self.unread_msgs.last(){
+            self.read_cursor = Some(msg.msg_id.clone());
+        }
+        self.unread_msgs.clear();
+    }
+
+    async fn replay(&mut self) -> Result<()>{
+        let mut replayer = match self.replayer.take(){
+            Some(replayer) => replayer,
+            None => {
+                let mut replayer = Subscriber::new(&random_seed(), self.walker_transport()?);
+                let link = create_link(&self.channel_address, &self.announcement_id)?;
+                with_retry!(self.retry_policy, replayer.receive_announcement(&link))?;
+                self.msg_cache.clear();
+                replayer
+            }
+        };
+
+        for msg in replayer.fetch_all_next_msgs().await{
+            if let Some(msg) = self.parse_msg(msg){
+                self.msg_cache.push(msg);
+            }
+        }
+        self.replayer = Some(replayer);
+        Ok(())
+    }
+
+    ///
+    /// Get the signed packet contained in a message, skipping the state messages and the packets of other publishers when the author is pinned
+    ///
+    fn parse_msg(&self, msg: UnwrappedMessage) -> Option<ReceivedMsg>{
+        let link = msg.link.rel();
+        let prev_msg_id = msg.prev_link.rel().to_string();
+        match msg.body{
+            MessageContent::SignedPacket {pk, public_payload, masked_payload } => {
+                if self.check_publisher(&pk).is_err(){
+                    return None;
+                }
+                let p = public_payload.0;
+                let m = masked_payload.0;
+
+                let is_state = parse_state_msg(&self.channel_address, &self.announcement_id, &p, &m).is_some();
+                match !is_state && (!p.is_empty() || !m.is_empty()){
+                    true => Some(ReceivedMsg::new(&link.to_string(), &public_key_hex(&pk), p, m, Some(&prev_msg_id))),
+                    false => None
+                }
+            }
+            _ => None
+        }
+    }
+
+    ///
+    /// Take the unread messages, moving the read cursor to the last one
+    ///
+    fn take_unread_msgs(&mut self) -> Vec<ReceivedMsg>{
+        let msgs = std::mem::take(&mut self.unread_msgs);
+        if let Some(msg) = msgs.last(){
+            self.read_cursor = Some(msg.msg_id.clone());
+        }
+        msgs
     }
 
     async fn fetch_next_msgs(&mut self) -> bool{
-        let msgs = self.subscriber.fetch_all_next_msgs().await;
-        let mut found = false;
+        !self.fetch_new_msgs().await.is_empty()
+    }
+
+    ///
+    /// Fetch the next messages, adding them to the unread ones and to the mirror.
+    /// When nothing is fetched because the node is not reachable, the fetch is repeated after reconnecting
+    ///
+    /// # Return Value
+    /// It returns the new messages
+    ///
+    pub(crate) async fn fetch_new_msgs(&mut self) -> Vec<ReceivedMsg>{
+        self.restore_primary().await;
+        let mut msgs = self.subscriber.fetch_all_next_msgs().await;
+        if msgs.is_empty() && !self.is_reachable().await && self.reconnect().await{
+            msgs = self.subscriber.fetch_all_next_msgs().await;
+        }
+        let mut new_msgs = vec![];
         for msg in msgs {
-            let link = msg.link.rel();
-            match msg.body{
-                MessageContent::SignedPacket {pk: _, public_payload, masked_payload } => {
-                    let p = public_payload.0;
-                    let m = masked_payload.0;
-
-                    if !p.is_empty() || !m.is_empty(){
-                        self.unread_msgs.push((link.to_string(), p, m));
-                        found = true;
-                    }
-                }
-                _ => {println!("{}", link.to_string());}
+            if let Some(msg) = self.parse_msg(msg){
+                new_msgs.push(msg);
             }
         }
-        found
+
+        // A failed mirroring doesn't invalidate the messages: they are mirrored again with the next fetch
+        if let Some(mirror) = &self.mirror{
+            self.unmirrored.extend(new_msgs.iter().cloned());
+            if mirror.record(&self.channel_address, &self.unmirrored).is_ok(){
+                self.unmirrored.clear();
+            }
+        }
+        self.unread_msgs.extend(new_msgs.iter().cloned());
+        new_msgs
+    }
+
+    pub(crate) fn unread_msgs(&self) -> &[ReceivedMsg]{
+        &self.unread_msgs
+    }
+}
+
+impl ChannelReader{
+    pub(crate) async fn import(channel_state: &ChannelState, psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
+        let node_pool = channel_state.node_pool(node_url, send_options);
+        ChannelReader::from_state(channel_state, psw, node_pool.client(), Some(node_pool)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::payload_serializers::{JsonPacket, JsonPacketBuilder, JsonSerializer};
+    use crate::channels::verify_proof;
+    use crate::transport::memory_transport::MemoryTransport;
+
+    fn packet(value: &str) -> JsonPacket{
+        JsonPacketBuilder::new().public(&value.to_string()).unwrap().build()
+    }
+
+    async fn open_channel(transport: &MemoryTransport) -> (ChannelWriter<MemoryTransport>, ChannelReader<MemoryTransport>){
+        let mut channel = ChannelWriter::builder().build_with_transport(transport.clone());
+        let (channel_address, announcement_id) = channel.open().await.unwrap();
+        let mut reader = ChannelReader::builder()
+            .pin_author(&channel.author_public_key())
+            .build_with_transport(&channel_address, &announcement_id, transport.clone());
+        reader.attach().await.unwrap();
+        (channel, reader)
+    }
+
+    #[tokio::test]
+    async fn pinned_author_is_kept_in_the_state() {
+        let transport = MemoryTransport::new();
+        let (channel, reader) = open_channel(&transport).await;
+        let state = ChannelState::decrypt(&reader.export_to_bytes("psw").await.unwrap(), "psw").unwrap();
+        assert_eq!(state.pinned_author(), Some(channel.author_public_key()));
+
+        let restored = ChannelReader::import_with_transport(&state, "psw", transport.clone()).await.unwrap();
+        let restored_state = ChannelState::decrypt(&restored.export_to_bytes("psw").await.unwrap(), "psw").unwrap();
+        assert_eq!(restored_state.pinned_author(), Some(channel.author_public_key()));
+    }
+
+    #[tokio::test]
+    async fn state_of_another_author_is_rejected() {
+        let transport = MemoryTransport::new();
+        let (_, reader) = open_channel(&transport).await;
+        let (other, _) = open_channel(&transport).await;
+        let state = ChannelState::decrypt(&reader.export_to_bytes("psw").await.unwrap(), "psw").unwrap()
+            .with_pinned_author(Some(&other.author_public_key()));
+        assert!(ChannelReader::import_with_transport(&state, "psw", transport).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn replay_resets_the_read_cursor() {
+        let transport = MemoryTransport::new();
+        let (mut channel, mut reader) = open_channel(&transport).await;
+        let first_id = channel.send_signed_packet(&packet("first")).await.unwrap();
+        assert_eq!(reader.fetch_raw_msgs().await.len(), 1);
+        let second_id = channel.send_signed_packet(&packet("second")).await.unwrap();
+
+        let msgs = reader.replay_from_start().await.unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].0, first_id);
+        assert_eq!(reader.read_cursor(), Some(second_id.clone()));
+        assert!(reader.fetch_raw_msgs().await.is_empty());
+
+        let third_id = channel.send_signed_packet(&packet("third")).await.unwrap();
+        let msgs = reader.replay_from(&second_id).await.unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(reader.read_cursor(), Some(third_id));
+        assert!(reader.fetch_raw_msgs().await.is_empty());
+
+        let (page, total) = reader.replay_page(1, 1).await.unwrap();
+        assert_eq!((page[0].0.clone(), total), (second_id, 3));
+    }
+
+    #[tokio::test]
+    async fn filter_reads_the_msgs_up_to_the_end_of_the_range() {
+        let transport = MemoryTransport::new();
+        let (mut channel, mut reader) = open_channel(&transport).await;
+        let first_id = channel.send_signed_packet(&packet("a")).await.unwrap();
+        channel.send_signed_packet(&packet("b")).await.unwrap();
+        let last_id = channel.send_signed_packet(&packet("c")).await.unwrap();
+        let unread_id = channel.send_signed_packet(&packet("d")).await.unwrap();
+
+        let filter = MessageFilter::new(|public: &String| public != "b").to_msg(&last_id);
+        let selected = reader.fetch_filtered::<JsonSerializer, String>(None, &filter).await.unwrap();
+        let selected_ids: Vec<String> = selected.into_iter().map(|(msg_id, _)| msg_id).collect();
+        assert_eq!(selected_ids, vec![first_id, last_id.clone()]);
+        assert_eq!(reader.read_cursor(), Some(last_id));
+
+        let unread = reader.fetch_raw_msgs().await;
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].0, unread_id);
+    }
+
+    #[tokio::test]
+    async fn filter_range_can_start_from_a_read_msg() {
+        let transport = MemoryTransport::new();
+        let (mut channel, mut reader) = open_channel(&transport).await;
+        let first_id = channel.send_signed_packet(&packet("a")).await.unwrap();
+        assert_eq!(reader.fetch_raw_msgs().await.len(), 1);
+        let last_id = channel.send_signed_packet(&packet("b")).await.unwrap();
+
+        let filter = MessageFilter::<String>::all().from_msg(&first_id);
+        let selected = reader.fetch_filtered::<JsonSerializer, String>(None, &filter).await.unwrap();
+        assert_eq!(selected.len(), 2);
+        assert_eq!(reader.read_cursor(), Some(last_id));
+        assert!(reader.fetch_raw_msgs().await.is_empty());
+
+        let filter = MessageFilter::<String>::all().from_msg("missing");
+        assert!(reader.fetch_filtered::<JsonSerializer, String>(None, &filter).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn time_range_uses_the_timestamp_of_the_msg() {
+        let transport = MemoryTransport::new();
+        let (mut channel, mut reader) = open_channel(&transport).await;
+        channel.send_signed_packet(&packet("a")).await.unwrap();
+        channel.send_signed_packet(&packet("b")).await.unwrap();
+
+        let filter = MessageFilter::<String>::all().time_range(0, 0);
+        assert!(reader.fetch_filtered::<JsonSerializer, String>(None, &filter).await.unwrap().is_empty());
+        assert!(reader.fetch_raw_msgs().await.is_empty());
+
+        channel.send_signed_packet(&packet("c")).await.unwrap();
+        let filter = MessageFilter::<String>::all().time_range(0, u64::MAX);
+        assert_eq!(reader.fetch_filtered::<JsonSerializer, String>(None, &filter).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn verified_chain_is_linked() {
+        let transport = MemoryTransport::new();
+        let (mut channel, mut reader) = open_channel(&transport).await;
+        let first_id = channel.send_signed_packet(&packet("first")).await.unwrap();
+        channel.send_signed_packet(&packet("second")).await.unwrap();
+        assert_eq!(reader.fetch_raw_msgs().await.len(), 2);
+
+        let report = reader.verify_chain(None).await.unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.msgs.len(), 2);
+        assert_eq!(report.msgs[1].prev_msg_id, first_id);
+
+        reader.read_cursor = Some("missing".to_string());
+        let report = reader.verify_chain(None).await.unwrap();
+        assert_eq!(report.issues, vec![ChainIssue::Gap{ msg_id: "missing".to_string() }]);
+    }
+
+    #[tokio::test]
+    async fn repeated_state_is_found_with_the_state_password() {
+        let transport = MemoryTransport::new();
+        let (mut channel, reader) = open_channel(&transport).await;
+        channel.send_signed_packet(&packet("first")).await.unwrap();
+        let (channel_address, announcement_id) = channel.channel_address();
+        let tag = state_msg_tag(&channel_address, &announcement_id);
+        let state = channel.export_to_bytes("psw").await.unwrap();
+        let first_id = channel.publish_tagged_state(&tag, &state).await.unwrap();
+        let copy = ChannelState::decrypt(&state, "psw").unwrap().encrypt("psw").unwrap();
+        let copy_id = channel.publish_tagged_state(&tag, &copy).await.unwrap();
+
+        assert!(reader.verify_chain(None).await.unwrap().is_valid());
+        let report = reader.verify_chain(Some("psw")).await.unwrap();
+        assert_eq!(report.issues, vec![ChainIssue::DuplicateState{ msg_id: copy_id, previous_msg_id: first_id }]);
+    }
+
+    #[tokio::test]
+    async fn proof_needs_the_author_of_the_channel() {
+        let transport = MemoryTransport::new();
+        let (mut channel, mut reader) = open_channel(&transport).await;
+        let (other, _) = open_channel(&transport).await;
+        let msg_id = channel.send_signed_packet(&packet("first")).await.unwrap();
+
+        let proof = reader.export_proof(&msg_id).await.unwrap();
+        assert_eq!(verify_proof(&proof, &channel.author_fingerprint()).await.unwrap(), channel.author_public_key());
+        assert!(verify_proof(&proof, &other.author_public_key()).await.is_err());
     }
 }
@@ -1,28 +1,53 @@
 use std::string::ToString;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use iota_streams::{
     app::transport::tangle::client::{Client as StreamsClient, SendOptions},
-    app_channels::api::tangle::Author,
+    app_channels::api::tangle::{Author, Subscriber},
 };
 
-use crate::channels::channel_state::ChannelState;
-use crate::payload::payload_serializers::{RawPacketBuilder, RawPacket};
+use crate::channels::channel_state::{ChannelState, current_timestamp, state_msg_tag, parse_state_msg};
+use crate::payload::payload_serializers::RawPacketBuilder;
 use crate::payload::payload_types::{StreamsPacket, StreamsPacketSerializer};
-use crate::user_builders::author_builder::AuthorBuilder;
-use crate::utility::iota_utility::{create_link, hash_string, msg_index};
-use crate::user_builders::subscriber_builder::SubscriberBuilder;
+use crate::utility::iota_utility::{create_link, hash_string, msg_index, random_seed, public_key_hex, public_key_fingerprint};
 use iota_streams::app_channels::api::tangle::MessageContent;
+use iota_streams::app_channels::api::ChannelType;
 use crate::channels::builders::channel_builders::ChannelWriterBuilder;
+use crate::channels::{AutosavePolicy, BatchSendError, Outbox, SnapshotPolicy, UnreachableNodeError};
+use iota_streams::ddml::types::Bytes;
+use crate::utility::retry_policy::{RetryPolicy, with_retry};
+use crate::utility::secrets::{SecretString, SymmetricKey};
+use crate::storage::state_store::StateStore;
+use zeroize::Zeroizing;
+use tokio::sync::Mutex;
+use crate::user_builders::node_pool::{NodePool, with_failover};
+use crate::user_builders::network::Network;
+use crate::transport::channel_transport::{ChannelTransport, announcement_reachable};
 
 ///
 /// Channel
 ///
-pub struct ChannelWriter {
-    author: Author<StreamsClient>,
+pub struct ChannelWriter<Trans: ChannelTransport = StreamsClient> {
+    author: Author<Trans>,
+    transport: Option<Trans>,
     channel_address: String,
     announcement_id: String,
-    last_msg_id: String
+    last_msg_id: String,
+    outbox: Option<Outbox>,
+    retry_policy: RetryPolicy,
+    node_pool: Option<NodePool>,
+    snapshot_policy: Option<SnapshotPolicy>,
+    msgs_since_snapshot: usize,
+    last_snapshot: Instant,
+    autosave: Option<AutosavePolicy>,
+    autosave_pending: bool,
+    last_autosave: Option<Instant>,
+    channel_type: Option<String>,
+    created_at: u64,
+    last_snapshot_id: Option<String>,
+    background_error: Option<anyhow::Error>,
 }
 
 impl ChannelWriter {
@@ -34,51 +59,176 @@ impl ChannelWriter {
         ChannelWriterBuilder::new()
     }
 
+    ///
+    /// Restore the channels from a previously stored byte array state
+    ///
+    pub async fn import_from_bytes(state: &[u8], psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
+        let psw = psw.into();
+        let channel_state = ChannelState::decrypt(&state, psw.expose())?;
+        let mut channel = ChannelWriter::import(&channel_state, psw.expose(), node_url, send_options).await?;
+        channel.check_update_state().await;
+        Ok(channel)
+    }
+
+    ///
+    /// Restore the channels from the state saved in a StateStore with the specified channel id
+    ///
+    pub async fn load_from(store: &dyn StateStore, channel_id: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
+        let psw = psw.into();
+        let state = store.load(channel_id)?;
+        ChannelWriter::import_from_bytes(&state, psw.expose(), node_url, send_options).await
+    }
+
+    ///
+    /// Restore the channels from a previously stored state in a file
+    ///
+    pub async fn import_from_file(file_path: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
+        let psw = psw.into();
+        let channel_state = ChannelState::from_file(file_path, psw.expose())?;
+        let mut channel = ChannelWriter::import(&channel_state, psw.expose(), node_url, send_options).await?;
+        channel.check_update_state().await;
+        Ok(channel)
+    }
+
+    ///
+    /// Restore the channels from a state file or, if it is missing or corrupted, from its most recent readable backup.
+    /// The channel is then updated to the last message attached to the tangle.
+    ///
+    /// # Return Value
+    /// It returns the channel and the backup generation the state was loaded from, 0 if it is the file itself
+    ///
+    pub async fn import_from_file_or_backup(file_path: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<(ChannelWriter, usize)>{
+        let psw = psw.into();
+        let (channel_state, generation) = ChannelState::from_file_or_backup(file_path, psw.expose())?;
+        let mut channel = ChannelWriter::import(&channel_state, psw.expose(), node_url, send_options).await?;
+        channel.check_update_state().await;
+        Ok((channel, generation))
+    }
+
+    ///
+    /// Restore the channels and its pending packets from an outbox file.
+    /// The packets that were already attached before the application stopped are removed from the outbox.
+    ///
+    pub async fn import_from_outbox(file_path: &str, psw: impl Into<SecretString>, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
+        let psw = psw.into();
+        let (channel_state, mut outbox) = Outbox::load(file_path, psw.expose())?;
+        let mut channel = ChannelWriter::import(&channel_state, psw.expose(), node_url, send_options).await?;
+        channel.outbox = Some(outbox);
+        channel.reconcile_outbox().await;
+        channel.persist_outbox().await?;
+        Ok(channel)
+    }
+
+    ///
+    /// Restore the channels from the most recent state message, saved by `open_and_save` or by the snapshot policy.
+    /// The whole channel is walked to find it: when the id of the last state message is known
+    /// (see `last_snapshot_id`) use `import_from_snapshot`, that reads only the messages after it.
+    /// The channel is read from the nodes of the specified network, or from the specified node.
+    /// Node errors are retried with the specified RetryPolicy, or the default one if None, that is set on the restored channel too
+    ///
+    pub async fn import_from_tangle(channel_id: &str, announce_id: &str, state_psw: impl Into<SecretString>, network: &Network, node_url: Option<&str>, send_options: Option<SendOptions>, retry_policy: Option<RetryPolicy>) -> Result<ChannelWriter>{
+        let state_psw = state_psw.into();
+        let retry_policy = retry_policy.unwrap_or_default();
+        let node_pool = NodePool::from_network(network).override_with(node_url, send_options.clone());
+        let state = match ChannelWriter::find_state(channel_id, announce_id, (channel_id, announce_id), None, &node_pool, &retry_policy).await?{
+            Some(state) => state,
+            None => return Err(anyhow::Error::msg("There is no state in the channels"))
+        };
+        ChannelWriter::import_with_retry(&state, state_psw.expose(), node_url, send_options, retry_policy).await
+    }
+
+    ///
+    /// Restore the channels from the specified state message, saved by `open_and_save` or by the snapshot policy.
+    /// Only the state message and the messages attached after it are fetched from the nodes of the specified network, or from the specified node.
+    /// Node errors are retried with the specified RetryPolicy, or the default one if None, that is set on the restored channel too
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_from_snapshot(channel_id: &str, announce_id: &str, snapshot_id: &str, state_psw: impl Into<SecretString>, network: &Network, node_url: Option<&str>, send_options: Option<SendOptions>, retry_policy: Option<RetryPolicy>) -> Result<ChannelWriter>{
+        let state_psw = state_psw.into();
+        let retry_policy = retry_policy.unwrap_or_default();
+        let node_pool = NodePool::from_network(network).override_with(node_url, send_options.clone());
+        let state = ChannelWriter::find_state(channel_id, announce_id, (channel_id, announce_id), Some(snapshot_id), &node_pool, &retry_policy).await?
+            .ok_or_else(|| anyhow::Error::msg("There is no state in the channels"))?;
+        ChannelWriter::import_with_retry(&state, state_psw.expose(), node_url, send_options, retry_policy).await
+    }
+
+    ///
+    /// Rebuild the channels from the seed of its author, when the state is lost.
+    /// The author is generated again from the seed, the announcement is located on the tangle
+    /// and the chain is walked to the last attached message.
+    ///
+    /// # Return Value
+    /// It returns the restored channel and its new state, with the author state encrypted with `state_psw`
+    ///
+    pub async fn recover_from_seed(seed: &str, channel_type: ChannelType, node_url: Option<&str>, state_psw: impl Into<SecretString>) -> Result<(ChannelWriter, ChannelState)>{
+        let state_psw = state_psw.into();
+        let mut builder = ChannelWriter::builder()
+            .seed(seed)
+            .channel_type(channel_type);
+        if let Some(node) = node_url{
+            builder = builder.node(node);
+        }
+        builder.build().recover(state_psw).await
+    }
+}
+
+impl<Trans: ChannelTransport> ChannelWriter<Trans> {
+
     ///
     /// Initialize the Channel
     ///
-    pub fn new(author: Author<StreamsClient>) -> ChannelWriter {
+    pub fn new(author: Author<Trans>) -> ChannelWriter<Trans> {
         let channel_address = author.channel_address().unwrap().to_string();
         ChannelWriter {
             author,
+            transport: None,
             channel_address,
             announcement_id: String::default(),
             last_msg_id: String::default(),
+            outbox: None,
+            retry_policy: RetryPolicy::default(),
+            node_pool: None,
+            snapshot_policy: None,
+            msgs_since_snapshot: 0,
+            last_snapshot: Instant::now(),
+            autosave: None,
+            autosave_pending: false,
+            last_autosave: None,
+            channel_type: None,
+            created_at: current_timestamp(),
+            last_snapshot_id: None,
+            background_error: None,
         }
     }
 
     ///
-    /// Restore the channels from a previously stored byte array state
+    /// Restore the channels from a state with the specified transport, for example a MemoryTransport.
+    /// The channel doesn't switch node on errors, since it has no nodes.
+    /// The channel is then updated to the last message attached to the tangle.
     ///
-    pub async fn import_from_bytes(state: &[u8], psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
-        let channel_state = ChannelState::decrypt(&state, &psw)?;
-        let mut channel = ChannelWriter::import(&channel_state, psw, node_url, send_options).await?;
+    pub async fn import_with_transport(channel_state: &ChannelState, psw: impl Into<SecretString>, transport: Trans) -> Result<ChannelWriter<Trans>>{
+        let psw = psw.into();
+        let mut channel = ChannelWriter::from_state(channel_state, psw.expose(), transport, None).await?;
         channel.check_update_state().await;
         Ok(channel)
     }
 
     ///
-    /// Restore the channels from a previously stored state in a file
+    /// Same as `recover_from_seed`, with the specified transport, for example a MemoryTransport
     ///
-    pub async fn import_from_file(file_path: &str, psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
-        let channel_state = ChannelState::from_file(file_path, &psw)?;
-        let mut channel = ChannelWriter::import(&channel_state, psw, node_url, send_options).await?;
-        channel.check_update_state().await;
-        Ok(channel)
-    }
-
-    pub async fn import_from_tangle(channel_id: &str, announce_id: &str, state_psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
-        match ChannelWriter::check_state(channel_id, announce_id, node_url).await{
-            Ok(state) => ChannelWriter::import_from_bytes(&state, state_psw, node_url, send_options).await,
-            Err(_) => Err(anyhow::Error::msg("There is no state in the channels"))
-        }
+    pub async fn recover_with_transport(seed: &str, channel_type: ChannelType, state_psw: impl Into<SecretString>, transport: Trans) -> Result<(ChannelWriter<Trans>, ChannelState)>{
+        ChannelWriter::builder()
+            .seed(seed)
+            .channel_type(channel_type)
+            .build_with_transport(transport)
+            .recover(state_psw.into()).await
     }
 
     ///
     /// Open a channels
     ///
     pub async fn open(&mut self) -> Result<(String, String)> {
-        let announce = self.author.send_announce().await?;
+        let announce = with_failover!(self, self.author.send_announce())?;
         self.announcement_id = announce.msgid.to_string();
         self.last_msg_id = self.announcement_id.clone();
         let res = (self.channel_address.clone(), self.announcement_id.clone());
@@ -89,83 +239,295 @@ impl ChannelWriter {
     ///
     /// Open a channels and save as first message the encrypted state of the channels itself
     ///
-    pub async fn open_and_save(&mut self, state_psw: &str) -> Result<(String, String, String)>{
+    pub async fn open_and_save(&mut self, state_psw: impl Into<SecretString>) -> Result<(String, String, String)>{
+        let state_psw = state_psw.into();
         let res = self.open().await?;
 
-        let public = format!("{}:{}.state", self.channel_address, self.announcement_id).as_bytes().to_vec();
-        let masked = self.export_to_bytes(state_psw).await?;
-
-        let state_msg_id = self.send_signed_raw_data(public, masked, None).await?;
-
+        let state_msg_id = self.publish_state(state_psw.expose()).await?;
         Ok((res.0, res.1, state_msg_id))
     }
 
     ///
     /// Write signed packet in a raw format.
     ///
-    pub async fn send_signed_raw_data(&mut self, p_data: Vec<u8>, m_data: Vec<u8>, key_nonce: Option<([u8;32], [u8;24])>) -> Result<String> {
-        let link_to = create_link(&self.channel_address, &self.last_msg_id)?;
-        let packet = match key_nonce{
-            None => RawPacketBuilder::new()
-                .public(&p_data)?
-                .masked(&m_data)?
-                .build(),
-            Some((key, nonce)) => RawPacketBuilder::new()
-                .public(&p_data)?
-                .masked(&m_data)?
-                .key_nonce(&key, &nonce)
-                .build()
-        };
-
-        let ret_link = self.author.send_signed_packet(
-            &link_to,
-            &packet.public_data()?,
-            &packet.masked_data()?,
-        ).await?;
+    pub async fn send_signed_raw_data(&mut self, p_data: Vec<u8>, m_data: Vec<u8>, key: Option<&SymmetricKey>) -> Result<String> {
+        let mut builder = RawPacketBuilder::new();
+        builder.public(&p_data)?.masked(&m_data)?;
+        if let Some(key) = key{
+            builder.key(key);
+        }
+        let packet = builder.build();
 
-        let msg_id = ret_link.0.msgid.to_string();
-        self.last_msg_id = msg_id.clone();
-        Ok(msg_id)
+        self.send_or_queue(packet.public_data()?, packet.masked_data()?).await
     }
 
     ///
     /// Write signed packet with formatted data.
+    /// The packets waiting in the outbox are sent first, to preserve the order of the channel.
+    /// If the node is not reachable the error is an `UnreachableNodeError`:
+    /// when the outbox is enabled the packet has been stored in it and it is sent later, check it with `UnreachableNodeError::is_queued(&err)`
     ///
     pub async fn send_signed_packet<T>(&mut self, packet: &StreamsPacket<T>) -> Result<String>
     where
         T: StreamsPacketSerializer,
     {
-        let link_to = create_link(&self.channel_address, &self.last_msg_id)?;
-        let (public_payload, masked_payload) = (packet.public_data()?, packet.masked_data()?);
+        self.send_or_queue(packet.public_data()?, packet.masked_data()?).await
+    }
 
-        let ret_link = self.author.send_signed_packet(
-            &link_to,
-            &public_payload,
-            &masked_payload,
-        ).await?;
+    ///
+    /// Write a batch of signed packets with formatted data.
+    /// Each packet is linked to the previous one, so they are attached in the same order of the slice.
+    ///
+    /// # Return Value
+    /// It returns the msg ids of the packets in the same order of the batch.
+    /// If a packet fails, the error contains the msg ids already published and the index from where to resume;
+    /// the last msg id of the channel always points to the last published packet.
+    ///
+    pub async fn send_batch<T>(&mut self, packets: &[StreamsPacket<T>]) -> std::result::Result<Vec<String>, BatchSendError>
+    where
+        T: StreamsPacketSerializer,
+    {
+        if let Err(e) = self.flush_pending().await{
+            return Err(BatchSendError::not_started(e));
+        }
+        let mut published = Vec::with_capacity(packets.len());
+        for packet in packets {
+            let payloads = packet.public_data().and_then(|p| Ok((p, packet.masked_data()?)));
+            let res = match payloads{
+                Ok((public_payload, masked_payload)) => self.send_payloads(&public_payload, &masked_payload).await,
+                Err(e) => Err(e)
+            };
 
-        let msg_id = ret_link.0.msgid.to_string();
-        self.last_msg_id = msg_id.clone();
-        Ok(msg_id)
+            match res{
+                Ok(msg_id) => published.push(msg_id),
+                Err(e) => return Err(BatchSendError::new(published, e))
+            }
+        }
+        Ok(published)
+    }
+
+    ///
+    /// Set the policy used to retry the operations that fail because of transient node errors
+    ///
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy){
+        self.retry_policy = retry_policy;
+    }
+
+    ///
+    /// Set the nodes used to switch node when the current one stops working
+    ///
+    pub fn set_node_pool(&mut self, node_pool: NodePool){
+        self.node_pool = Some(node_pool);
+    }
+
+    ///
+    /// Get the url of the node currently used by the channel
+    ///
+    pub fn current_node(&self) -> Option<String>{
+        self.node_pool.as_ref().map(|pool| pool.current().to_string())
+    }
+
+    ///
+    /// Get the network of the nodes used by the channel
+    ///
+    pub fn network(&self) -> Network{
+        match &self.node_pool{
+            None => Network::default(),
+            Some(pool) => pool.network()
+        }
+    }
+
+    ///
+    /// Set the policy used to publish periodically the encrypted state of the channels
+    ///
+    pub fn set_snapshot_policy(&mut self, snapshot_policy: SnapshotPolicy){
+        self.snapshot_policy = Some(snapshot_policy);
+    }
+
+    ///
+    /// Set the policy used to save the channels state after each message successfully sent
+    ///
+    pub fn set_autosave_policy(&mut self, autosave: AutosavePolicy){
+        self.autosave = Some(autosave);
+    }
+
+    ///
+    /// Save the channels state if there are messages sent after the last autosave, ignoring the debounce interval
+    ///
+    pub async fn flush_autosave(&mut self) -> Result<()>{
+        if !self.autosave_pending{
+            return Ok(());
+        }
+        let policy = match &self.autosave{
+            None => return Ok(()),
+            Some(policy) => policy
+        };
+
+        let channel_state = self.export(policy.psw()).await?;
+        policy.save(&channel_state)?;
+        self.autosave_pending = false;
+        self.last_autosave = Some(Instant::now());
+        Ok(())
+    }
+
+    ///
+    /// Check if there are messages sent that are not saved by the autosave yet
+    ///
+    pub fn autosave_pending(&self) -> bool{
+        self.autosave_pending
+    }
+
+    ///
+    /// Enable the outbox mode: packets that cannot be attached are stored in the specified file,
+    /// together with the channel state encrypted with the specified password.
+    ///
+    pub fn enable_outbox(&mut self, file_path: &str, psw: impl Into<SecretString>){
+        self.outbox = Some(Outbox::new(file_path, psw));
+    }
+
+    ///
+    /// Publish in order the packets stored in the outbox.
+    /// The outbox is saved after each attached packet, so a restart never sends the same packet twice.
+    ///
+    /// # Return Value
+    /// It returns the ids of the attached messages.
+    ///
+    pub async fn flush_outbox(&mut self) -> Result<Vec<String>>{
+        let mut published = vec![];
+        if self.outbox_len() > 0{
+            // The last packet may have been attached even if the node didn't answer
+            self.reconcile_outbox().await;
+        }
+        loop{
+            let (public, masked) = match self.outbox.as_ref().and_then(|o| o.front()){
+                None => return Ok(published),
+                Some((p, m)) => (Bytes(p.clone()), Bytes(m.clone()))
+            };
+
+            let msg_id = self.send_payloads(&public, &masked).await?;
+            if let Some(outbox) = &mut self.outbox{
+                outbox.pop_front();
+            }
+            self.persist_outbox().await?;
+            published.push(msg_id);
+        }
+    }
+
+    ///
+    /// Flush the outbox of a shared writer at each interval, until the future is dropped.
+    /// The packets that can't be sent are tried again at the next interval, and the error is kept in `take_background_error`.
+    /// Run it in a task of the application (i.e. with `tokio::task::spawn_local`) and abort the task to stop it.
+    ///
+    pub async fn flush_outbox_periodically(writer: Arc<Mutex<ChannelWriter<Trans>>>, interval: Duration){
+        loop{
+            tokio::time::sleep(interval).await;
+            let mut writer = writer.lock().await;
+            if writer.outbox_len() == 0{
+                continue;
+            }
+            if let Err(e) = writer.flush_outbox().await{
+                writer.background_error = Some(e);
+            }
+        }
+    }
+
+    ///
+    /// Save the state of a shared writer when the debounce interval of the autosave expires after a skipped save,
+    /// until the future is dropped, so the last messages are saved even if no other message is sent.
+    /// The error is kept in `take_background_error` and the save is tried again at the next interval.
+    /// Run it in a task of the application (i.e. with `tokio::task::spawn_local`) and abort the task to stop it.
+    ///
+    pub async fn flush_autosave_periodically(writer: Arc<Mutex<ChannelWriter<Trans>>>){
+        loop{
+            let wait = writer.lock().await.autosave_wait();
+            tokio::time::sleep(wait).await;
+            let mut writer = writer.lock().await;
+            if !writer.autosave_pending || !writer.autosave_due(){
+                continue;
+            }
+            if let Err(e) = writer.flush_autosave().await{
+                writer.background_error = Some(e.context("The state has not been saved by the autosave"));
+            }
+        }
+    }
+
+    ///
+    /// Get the last error of the operations done in background by the writer: the periodic flush of the outbox,
+    /// the autosave and the snapshots of the state, that don't make the sent message fail
+    ///
+    pub fn take_background_error(&mut self) -> Option<anyhow::Error>{
+        self.background_error.take()
+    }
+
+    ///
+    /// Get the number of packets waiting in the outbox
+    ///
+    pub fn outbox_len(&self) -> usize{
+        match &self.outbox{
+            None => 0,
+            Some(outbox) => outbox.len()
+        }
     }
 
     ///
     /// Export the channels state into an encrypted byte array.
     ///
-    pub async fn export_to_bytes(&self, psw: &str)-> Result<Vec<u8>>{
-        let channel_state = self.export(psw).await?;
-        channel_state.encrypt(psw)
+    pub async fn export_to_bytes(&self, psw: impl Into<SecretString>)-> Result<Vec<u8>>{
+        let psw = psw.into();
+        let channel_state = self.export(psw.expose()).await?;
+        channel_state.encrypt(psw.expose())
+    }
+
+    ///
+    /// Save the encrypted channels state in a StateStore, using the channel address as id
+    ///
+    pub async fn persist_to(&self, store: &dyn StateStore, psw: impl Into<SecretString>) -> Result<()>{
+        let psw = psw.into();
+        let state = self.export_to_bytes(psw.expose()).await?;
+        store.save(&self.channel_address, &state)
     }
 
     ///
     /// Stores the channels state in a file. The author state is encrypted with the specified password
     ///
-    pub async fn export_to_file(&self, psw: &str, file_path: &str)-> Result<()>{
-        let channel_state = self.export(psw).await?;
-        channel_state.write_to_file(file_path, psw)?;
+    pub async fn export_to_file(&self, psw: impl Into<SecretString>, file_path: &str)-> Result<()>{
+        let psw = psw.into();
+        let channel_state = self.export(psw.expose()).await?;
+        channel_state.write_to_file(file_path, psw.expose())?;
         Ok(())
     }
 
+    ///
+    /// Stores the channels state in a file, keeping up to `backups` generations of the previous states
+    ///
+    pub async fn export_to_file_with_backups(&self, psw: impl Into<SecretString>, file_path: &str, backups: usize)-> Result<()>{
+        let psw = psw.into();
+        let channel_state = self.export(psw.expose()).await?;
+        channel_state.write_to_file_with_backups(file_path, psw.expose(), backups)
+    }
+
+    ///
+    /// Get the hex encoded Ed25519 public key of the author, used by the readers to pin the channel
+    ///
+    pub fn author_public_key(&self) -> String{
+        public_key_hex(self.author.get_pk())
+    }
+
+    ///
+    /// Get the fingerprint of the public key of the author
+    ///
+    pub fn author_fingerprint(&self) -> String{
+        public_key_fingerprint(self.author.get_pk())
+    }
+
+    ///
+    /// Get the id of the last state message published by `open_and_save` or by the snapshot policy.
+    /// Keep it to restore the channel with `import_from_snapshot`
+    ///
+    pub fn last_snapshot_id(&self) -> Option<String>{
+        self.last_snapshot_id.clone()
+    }
+
     ///
     /// Get the channels address and the announcement id
     ///
@@ -182,64 +544,621 @@ impl ChannelWriter {
     }
 }
 
-impl ChannelWriter{
-    async fn check_update_state(&mut self){
+impl<Trans: ChannelTransport> ChannelWriter<Trans>{
+    pub(crate) fn set_channel_type(&mut self, channel_type: &str){
+        self.channel_type = Some(channel_type.to_string());
+    }
+
+    ///
+    /// Send the packets of the outbox and then the new one, or store it in the outbox if the node is not reachable.
+    /// The other errors, that would happen again when the packet is sent later, are returned and the packet is not stored
+    ///
+    async fn send_or_queue(&mut self, public_payload: Bytes, masked_payload: Bytes) -> Result<String>{
+        if self.outbox.is_none(){
+            return self.send_payloads(&public_payload, &masked_payload).await;
+        }
+
+        let res = match self.flush_outbox().await{
+            Ok(_) => self.send_payloads(&public_payload, &masked_payload).await,
+            Err(e) => Err(e)
+        };
+        match res{
+            Ok(msg_id) => {
+                self.persist_outbox().await?;
+                Ok(msg_id)
+            }
+            Err(e) if UnreachableNodeError::is_unreachable(&e) => {
+                if let Some(outbox) = &mut self.outbox{
+                    outbox.push(public_payload.0, masked_payload.0);
+                }
+                self.persist_outbox().await?;
+                Err(UnreachableNodeError::into_queued(e))
+            }
+            Err(e) => {
+                self.persist_outbox().await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn send_payloads(&mut self, public_payload: &Bytes, masked_payload: &Bytes) -> Result<String>{
+        let msg_id = self.attach_payloads(public_payload, masked_payload).await?;
+        self.msgs_since_snapshot += 1;
+
+        let snapshot_psw = match &self.snapshot_policy{
+            Some(policy) if policy.is_due(self.msgs_since_snapshot, self.last_snapshot) => Some(SecretString::new(policy.state_psw())),
+            _ => None
+        };
+        // A failed snapshot or autosave doesn't invalidate the message: they are done again with the next one
+        // and the error is kept for the application
+        if let Some(psw) = snapshot_psw{
+            if let Err(e) = self.publish_state(psw.expose()).await{
+                self.background_error = Some(e.context("The snapshot of the state has not been published"));
+            }
+        }
+
+        self.autosave_pending = self.autosave.is_some();
+        if self.autosave_due(){
+            if let Err(e) = self.flush_autosave().await{
+                self.background_error = Some(e.context("The state has not been saved by the autosave"));
+            }
+        }
+        Ok(msg_id)
+    }
+
+    async fn publish_state(&mut self, state_psw: &str) -> Result<String>{
+        let tag = state_msg_tag(&self.channel_address, &self.announcement_id);
+        let state = self.export_to_bytes(state_psw).await?;
+        let msg_id = self.publish_tagged_state(&tag, &state).await?;
+        self.msgs_since_snapshot = 0;
+        self.last_snapshot = Instant::now();
+        self.last_snapshot_id = Some(msg_id.clone());
+        Ok(msg_id)
+    }
+
+    ///
+    /// Publish an encrypted state as a state message with the specified tag
+    ///
+    pub(crate) async fn publish_tagged_state(&mut self, tag: &str, state: &[u8]) -> Result<String>{
+        let packet = RawPacketBuilder::new()
+            .public(&tag.as_bytes().to_vec())?
+            .masked(&state.to_vec())?
+            .build();
+
+        self.attach_payloads(&packet.public_data()?, &packet.masked_data()?).await
+    }
+
+    async fn attach_payloads(&mut self, public_payload: &Bytes, masked_payload: &Bytes) -> Result<String>{
+        self.restore_primary().await;
+        let mut attempt = 0;
+        let mut failed_over = false;
+        loop{
+            let link_to = create_link(&self.channel_address, &self.last_msg_id)?;
+            let err = match self.author.send_signed_packet(&link_to, public_payload, masked_payload).await{
+                Ok(ret_link) => {
+                    let msg_id = ret_link.0.msgid.to_string();
+                    self.last_msg_id = msg_id.clone();
+                    return Ok(msg_id);
+                }
+                Err(e) => e
+            };
+
+            // The node may have attached the packet even if the send failed:
+            // it is sent again only when the channel can be read and the packet is not there
+            if !self.is_reachable().await && !self.reconnect().await{
+                return Err(UnreachableNodeError::new(err).into());
+            }
+            if let Some(msg_id) = self.find_attached(public_payload, masked_payload).await{
+                return Ok(msg_id);
+            }
+
+            match self.retry_policy.next_delay(attempt, &err){
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => {
+                    if failed_over || !self.failover().await?{
+                        return Err(err);
+                    }
+                    failed_over = true;
+                    attempt = 0;
+                }
+            }
+        }
+    }
+
+    async fn failover(&mut self) -> Result<bool>{
+        let mut pool = match &self.node_pool{
+            None => return Ok(false),
+            Some(pool) => pool.clone()
+        };
+        if pool.next_healthy().await.is_none(){
+            return Ok(false);
+        }
+        self.connect_to(pool).await
+    }
+
+    ///
+    /// Go back to the primary node of the pool when it is healthy again, with the PrimaryBackup strategy
+    ///
+    /// # Return Value
+    /// It returns true if the author moved to the primary node
+    ///
+    async fn restore_primary(&mut self) -> bool{
+        let mut pool = match &self.node_pool{
+            None => return false,
+            Some(pool) => pool.clone()
+        };
+        if !pool.restore_primary().await{
+            // Keep the time of the last check of the primary node
+            self.node_pool = Some(pool);
+            return false;
+        }
+        // If the author can't be moved it stays on the current node, and the primary node is checked again with the next operation
+        self.connect_to(pool).await.unwrap_or(false)
+    }
+
+    ///
+    /// Move the author to the current node of the pool, that replaces the pool of the channel
+    ///
+    async fn connect_to(&mut self, pool: NodePool) -> Result<bool>{
+        let transport = match Trans::connect(&pool){
+            None => return Ok(false),
+            Some(transport) => transport
+        };
+
+        let psw = SecretString::from(random_seed());
+        let author_state = Zeroizing::new(self.author.export(psw.expose()).await?);
+        self.author = Author::import(&author_state, psw.expose(), transport.clone()).await?;
+        self.transport = Some(transport);
+        self.node_pool = Some(pool);
+        Ok(true)
+    }
+
+    ///
+    /// Locate the announcement generated by the seed of the author and walk the chain to the last attached message
+    ///
+    async fn recover(mut self, state_psw: SecretString) -> Result<(ChannelWriter<Trans>, ChannelState)>{
+        let announce_link = match self.author.announcement_link(){
+            Some(link) => link.clone(),
+            None => return Err(anyhow::Error::msg("Unable to generate the announcement of the channels"))
+        };
+        let transport = match &self.transport{
+            Some(transport) => transport.clone(),
+            None => return Err(anyhow::Error::msg("The channels has no transport"))
+        };
+        let mut subscriber = Subscriber::new(&random_seed(), transport);
+        if with_retry!(self.retry_policy, subscriber.receive_announcement(&announce_link)).is_err(){
+            return Err(anyhow::Error::msg("There is no channels announced with this seed"));
+        }
+
+        self.announcement_id = announce_link.msgid.to_string();
+        self.last_msg_id = self.announcement_id.clone();
+        self.check_update_state().await;
+
+        let channel_state = self.export(state_psw.expose()).await?;
+        Ok((self, channel_state))
+    }
+
+    pub(crate) fn set_transport(&mut self, transport: Trans){
+        self.transport = Some(transport);
+    }
+
+    async fn is_reachable(&self) -> bool{
+        let transport = match &self.transport{
+            Some(transport) => transport.clone(),
+            None => match Trans::connect(&self.node_pool.clone().unwrap_or_default()){
+                Some(transport) => transport,
+                None => return false
+            }
+        };
+        announcement_reachable(transport, &self.channel_address, &self.announcement_id).await
+    }
+
+    ///
+    /// Wait until the node is reachable again, following the retry policy, and then switch node
+    ///
+    /// # Return Value
+    /// It returns true if the channel can reach the tangle again
+    ///
+    async fn reconnect(&mut self) -> bool{
+        let err = anyhow::Error::msg("The node is not reachable");
+        let mut attempt = 0;
+        while let Some(delay) = self.retry_policy.next_delay(attempt, &err){
+            tokio::time::sleep(delay).await;
+            if self.is_reachable().await{
+                return true;
+            }
+            attempt += 1;
+        }
+        matches!(self.failover().await, Ok(true))
+    }
+
+    ///
+    /// Check if a packet whose send failed has been attached anyway, as the last message of the channel
+    ///
+    async fn find_attached(&mut self, public_payload: &Bytes, masked_payload: &Bytes) -> Option<String>{
+        let found = self.check_update_state().await;
+        match found.last(){
+            Some((public, masked)) if *public == public_payload.0 && *masked == masked_payload.0 => Some(self.last_msg_id.clone()),
+            _ => None
+        }
+    }
+
+    ///
+    /// Fetch the messages attached after the last known one, moving the last msg id.
+    /// When nothing is fetched because the node is not reachable, the fetch is repeated after reconnecting
+    ///
+    async fn check_update_state(&mut self) -> Vec<(Vec<u8>, Vec<u8>)>{
+        let mut found = vec![];
         loop{
             let mut msgs = self.author.fetch_next_msgs().await;
+            if msgs.is_empty() && !self.is_reachable().await && self.reconnect().await{
+                msgs = self.author.fetch_next_msgs().await;
+            }
             if msgs.is_empty(){break;}
-            let last_msg = match msgs.pop(){
-                None => return,
-                Some(m) => m
-            };
-            self.last_msg_id = last_msg.link.msgid.to_string();
+            for msg in msgs {
+                self.last_msg_id = msg.link.msgid.to_string();
+                if let MessageContent::SignedPacket { public_payload, masked_payload, .. } = msg.body{
+                    found.push((public_payload.0, masked_payload.0));
+                }
+            }
         }
+        found
     }
 
-    async fn import(channel_state: &ChannelState, psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
-        let author = AuthorBuilder::build_from_state(
-            &channel_state.user_state(),
-            psw,
-            node_url,
-            send_options
-        ).await?;
+    ///
+    /// Remove from the outbox the packets found on the channel, attached even if the node didn't answer
+    ///
+    async fn reconcile_outbox(&mut self){
+        for (public, masked) in self.check_update_state().await {
+            if let Some(outbox) = &mut self.outbox{
+                if matches!(outbox.front(), Some((p, m)) if *p == public && *m == masked){
+                    outbox.pop_front();
+                }
+            }
+        }
+    }
+
+    fn autosave_due(&self) -> bool{
+        match &self.autosave{
+            Some(policy) => policy.is_due(self.last_autosave),
+            None => false
+        }
+    }
+
+    ///
+    /// Time to wait before the next periodic autosave
+    ///
+    fn autosave_wait(&self) -> Duration{
+        match &self.autosave{
+            Some(policy) if self.autosave_pending => policy.remaining(self.last_autosave),
+            Some(policy) => policy.remaining(Some(Instant::now())),
+            None => Duration::from_secs(1)
+        }
+    }
+
+    ///
+    /// Send the packets waiting in the outbox, if any
+    ///
+    async fn flush_pending(&mut self) -> Result<()>{
+        if self.outbox_len() > 0{
+            self.flush_outbox().await?;
+        }
+        Ok(())
+    }
+
+    async fn persist_outbox(&self) -> Result<()>{
+        let outbox = match &self.outbox{
+            None => return Ok(()),
+            Some(outbox) => outbox
+        };
+        let channel_state = self.export(outbox.psw()).await?;
+        outbox.save(channel_state)
+    }
+
+    pub(crate) async fn from_state(channel_state: &ChannelState, psw: &str, transport: Trans, node_pool: Option<NodePool>) -> Result<ChannelWriter<Trans>>{
+        if channel_state.is_reader(){
+            return Err(anyhow::Error::msg("The state belongs to a ChannelReader"));
+        }
+        let psw_hash = SecretString::from(hash_string(psw));
+        let author = Author::import(&channel_state.user_state(), psw_hash.expose(), transport.clone()).await?;
         let channel_address = author.channel_address().unwrap().to_string();
 
         Ok(ChannelWriter {
             author,
+            transport: Some(transport),
             channel_address,
             announcement_id: channel_state.announcement_id(),
             last_msg_id: channel_state.last_msg_id(),
+            outbox: None,
+            retry_policy: RetryPolicy::default(),
+            node_pool,
+            snapshot_policy: None,
+            msgs_since_snapshot: 0,
+            last_snapshot: Instant::now(),
+            autosave: None,
+            autosave_pending: false,
+            last_autosave: None,
+            channel_type: channel_state.channel_type(),
+            created_at: channel_state.created_at(),
+            last_snapshot_id: channel_state.last_snapshot_id(),
+            background_error: None,
         })
     }
 
-    async fn export(&self, psw: &str) -> Result<ChannelState>{
-        let psw_hash = hash_string(psw);
-        let author_state = self.author.export(&psw_hash).await?;
-        Ok(ChannelState::new(&author_state, &self.channel_address, &self.announcement_id, &self.last_msg_id))
+    pub(crate) async fn export(&self, psw: &str) -> Result<ChannelState>{
+        let psw_hash = SecretString::from(hash_string(psw));
+        let author_state = Zeroizing::new(self.author.export(psw_hash.expose()).await?);
+        let channel_state = ChannelState::new(&author_state, &self.channel_address, &self.announcement_id, &self.last_msg_id, &self.network())
+            .with_created_at(self.created_at)
+            .with_last_snapshot_id(self.last_snapshot_id.as_deref());
+        let channel_state = match &self.node_pool{
+            None => channel_state,
+            Some(node_pool) => channel_state.with_node_pool(node_pool)
+        };
+        match &self.channel_type{
+            None => Ok(channel_state),
+            Some(channel_type) => Ok(channel_state.with_channel_type(channel_type))
+        }
     }
+}
 
-    async fn check_state(channel_id: &str, announce_id: &str, node_url: Option<&str>) -> Result<Vec<u8>>{
-        let mut subscriber = match node_url{
-            None => SubscriberBuilder::new().build(),
-            Some(node) => SubscriberBuilder::new().node(node).build()
-        };
-        subscriber.receive_announcement(&create_link(channel_id, announce_id)?).await?;
-        match subscriber.fetch_next_msgs().await.pop(){
-            None => return Err(anyhow::Error::msg("There is no state in the channels")),
-            Some(m) => {
-                match m.body{
-                    MessageContent::SignedPacket { public_payload, masked_payload, .. } => {
-                        let comp = format!("{}:{}.state", channel_id, announce_id);
-                        let (public, masked): (String, Vec<u8>) = RawPacket::from_streams_response(&public_payload.0, &masked_payload.0, &None)?
-                            .deserialize()?;
-                        if public != comp{
-                            return Err(anyhow::Error::msg("There is no state in the channels"))
-                        }
-                        Ok(masked)
+impl ChannelWriter{
+    pub(crate) async fn import(channel_state: &ChannelState, psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
+        let node_pool = channel_state.node_pool(node_url, send_options);
+        ChannelWriter::from_state(channel_state, psw, node_pool.client(), Some(node_pool)).await
+    }
+
+    ///
+    /// Restore the channels from an encrypted state with the retry policy, that is used while it is updated too
+    ///
+    async fn import_with_retry(state: &[u8], psw: &str, node_url: Option<&str>, send_options: Option<SendOptions>, retry_policy: RetryPolicy) -> Result<ChannelWriter>{
+        let channel_state = ChannelState::decrypt(state, psw)?;
+        let mut channel = ChannelWriter::import(&channel_state, psw, node_url, send_options).await?;
+        channel.set_retry_policy(retry_policy);
+        channel.check_update_state().await;
+        Ok(channel)
+    }
+
+    ///
+    /// Get a state message of the owner channel, identified by its channel id and announcement id, published in a channel.
+    /// If the id of the state message is not known, the channel is walked to find the most recent one.
+    /// The walk is not retried, since the fetch of the subscriber doesn't return the errors:
+    /// it fails if the node is not reachable when the walk ends, since an older state would be returned
+    ///
+    /// # Return Value
+    /// It returns the state, or None if the channel has no state message of the owner
+    ///
+    pub(crate) async fn find_state(channel_id: &str, announce_id: &str, owner: (&str, &str), state_msg_id: Option<&str>, node_pool: &NodePool, retry_policy: &RetryPolicy) -> Result<Option<Vec<u8>>>{
+        let client = node_pool.client();
+        let mut subscriber = Subscriber::new(&random_seed(), client.clone());
+        let announce_link = create_link(channel_id, announce_id)?;
+        with_retry!(retry_policy, subscriber.receive_announcement(&announce_link))?;
+
+        if let Some(msg_id) = state_msg_id{
+            let msg_link = create_link(channel_id, msg_id)?;
+            let (pk, public_payload, masked_payload) = with_retry!(retry_policy, subscriber.receive_signed_packet(&msg_link))?;
+            if subscriber.author_public_key() != Some(&pk){
+                return Err(anyhow::Error::msg("The state message is not signed by the author of the channels"));
+            }
+            return match parse_state_msg(owner.0, owner.1, &public_payload.0, &masked_payload.0){
+                Some(state) => Ok(Some(state)),
+                None => Err(anyhow::Error::msg(format!("The message {} is not a state message of the channels", msg_id)))
+            };
+        }
+
+        let mut last_state = None;
+        let author = subscriber.author_public_key().cloned();
+        loop{
+            let msgs = subscriber.fetch_all_next_msgs().await;
+            if msgs.is_empty(){
+                // An empty fetch is the end of the channel only if the node can be reached
+                if !announcement_reachable(client.clone(), channel_id, announce_id).await{
+                    return Err(anyhow::Error::msg("The channels has not been read to the last message, since the node is not reachable"));
+                }
+                break;
+            }
+            for msg in msgs {
+                if let MessageContent::SignedPacket { pk, public_payload, masked_payload } = msg.body{
+                    if author.as_ref() != Some(&pk){
+                        continue;
+                    }
+                    if let Some(state) = parse_state_msg(owner.0, owner.1, &public_payload.0, &masked_payload.0){
+                        last_state = Some(state);
                     }
-                    _ => return Err(anyhow::Error::msg("There is no state in the channels"))
                 }
             }
         }
+
+        Ok(last_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_state_store::MemoryStateStore;
+    use std::time::Duration;
+    use crate::channels::ChannelReader;
+    use crate::payload::payload_serializers::{JsonPacket, JsonPacketBuilder};
+    use crate::transport::memory_transport::MemoryTransport;
+
+    async fn open_channel(transport: &MemoryTransport, retry_policy: RetryPolicy) -> ChannelWriter<MemoryTransport>{
+        let mut channel = ChannelWriter::builder()
+            .retry_policy(retry_policy)
+            .build_with_transport(transport.clone());
+        channel.open().await.unwrap();
+        channel
+    }
+
+    fn packet(value: &str) -> JsonPacket{
+        JsonPacketBuilder::new().public(&value.to_string()).unwrap().build()
+    }
+
+    fn fast_retry() -> RetryPolicy{
+        RetryPolicy::new().max_attempts(3).base_delay(Duration::from_millis(1)).jitter(false)
+    }
+
+    #[tokio::test]
+    async fn batch_is_attached_in_order() {
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, fast_retry()).await;
+        let msg_ids = channel.send_batch(&[packet("first"), packet("second"), packet("third")]).await.unwrap();
+        assert_eq!(msg_ids.len(), 3);
+
+        let (channel_address, announcement_id) = channel.channel_address();
+        let mut reader = ChannelReader::builder().build_with_transport(&channel_address, &announcement_id, transport.clone());
+        reader.attach().await.unwrap();
+        let received: Vec<String> = reader.fetch_raw_msgs().await.into_iter().map(|(msg_id, _, _)| msg_id).collect();
+        assert_eq!(received, msg_ids);
+    }
+
+    #[tokio::test]
+    async fn interrupted_batch_is_resumed() {
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, fast_retry()).await;
+        let packets = [packet("first"), packet("second")];
+
+        transport.set_online(false);
+        let err = channel.send_batch(&packets).await.unwrap_err();
+        assert!(err.published().is_empty());
+        assert_eq!(err.resume_index(), 0);
+        assert!(err.attach_unknown());
+        transport.set_online(true);
+
+        let msg_ids = channel.send_batch(&packets[err.resume_index()..]).await.unwrap();
+        assert_eq!(msg_ids.len(), 2);
+        assert_eq!(transport.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn channel_is_recovered_from_the_seed() {
+        let transport = MemoryTransport::new();
+        let seed = random_seed();
+        let mut channel = ChannelWriter::builder().seed(&seed).build_with_transport(transport.clone());
+        let (channel_address, announcement_id) = channel.open().await.unwrap();
+        channel.send_signed_packet(&packet("first")).await.unwrap();
+        let last_msg_id = channel.send_signed_packet(&packet("second")).await.unwrap();
+
+        let (mut recovered, state) = ChannelWriter::recover_with_transport(&seed, ChannelType::SingleBranch, "psw", transport.clone()).await.unwrap();
+        assert_eq!(recovered.channel_address(), (channel_address, announcement_id));
+        assert_eq!(state.last_msg_id(), last_msg_id);
+
+        recovered.send_signed_packet(&packet("third")).await.unwrap();
+        assert_eq!(transport.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn seed_without_channel_is_not_recovered() {
+        let transport = MemoryTransport::new();
+        open_channel(&transport, fast_retry()).await;
+        let recovered = ChannelWriter::recover_with_transport(&random_seed(), ChannelType::SingleBranch, "psw", transport).await;
+        assert!(recovered.is_err());
+    }
+
+    #[tokio::test]
+    async fn lost_answer_is_not_sent_twice() {
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, fast_retry()).await;
+
+        transport.set_lose_answers(true);
+        let msg_id = channel.send_signed_packet(&packet("first")).await.unwrap();
+        transport.set_lose_answers(false);
+        assert_eq!(transport.len(), 2);
+
+        let next_id = channel.send_signed_packet(&packet("second")).await.unwrap();
+        assert_ne!(msg_id, next_id);
+        assert_eq!(transport.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn unreachable_node_is_not_retried() {
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, fast_retry()).await;
+
+        transport.set_online(false);
+        let err = channel.send_signed_packet(&packet("first")).await.unwrap_err();
+        assert!(UnreachableNodeError::is_unreachable(&err));
+        transport.set_online(true);
+        assert_eq!(transport.len(), 1);
+
+        channel.send_signed_packet(&packet("first")).await.unwrap();
+        assert_eq!(transport.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unreachable_packets_are_queued_and_sent_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let outbox_path = dir.path().join("outbox");
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, fast_retry()).await;
+        channel.enable_outbox(outbox_path.to_str().unwrap(), "psw");
+
+        transport.set_online(false);
+        let err = channel.send_signed_packet(&packet("first")).await.unwrap_err();
+        assert!(UnreachableNodeError::is_queued(&err));
+        let err = channel.send_signed_packet(&packet("second")).await.unwrap_err();
+        assert!(UnreachableNodeError::is_queued(&err));
+        assert_eq!(channel.outbox_len(), 2);
+        transport.set_online(true);
+
+        channel.send_signed_packet(&packet("third")).await.unwrap();
+        assert_eq!(channel.outbox_len(), 0);
+        assert_eq!(transport.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn skipped_autosave_is_done_when_the_debounce_expires() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("channel.state");
+        let state_path = state_path.to_str().unwrap();
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, RetryPolicy::default()).await;
+        channel.set_autosave_policy(AutosavePolicy::to_file(state_path, "psw").debounce(Duration::from_millis(50)));
+
+        channel.send_signed_packet(&packet("first")).await.unwrap();
+        let last_msg_id = channel.send_signed_packet(&packet("second")).await.unwrap();
+        assert!(channel.autosave_pending());
+
+        let channel = Arc::new(Mutex::new(channel));
+        let flush = ChannelWriter::flush_autosave_periodically(channel.clone());
+        assert!(tokio::time::timeout(Duration::from_millis(500), flush).await.is_err());
+        assert!(!channel.lock().await.autosave_pending());
+        assert_eq!(ChannelState::from_file(state_path, "psw").unwrap().last_msg_id(), last_msg_id);
+    }
+
+    #[tokio::test]
+    async fn failed_autosave_is_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("missing").join("channel.state");
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport, RetryPolicy::default()).await;
+        channel.set_autosave_policy(AutosavePolicy::to_file(state_path.to_str().unwrap(), "psw"));
+
+        channel.send_signed_packet(&packet("first")).await.unwrap();
+        assert!(channel.take_background_error().is_some());
+        assert!(channel.autosave_pending());
+        assert!(channel.take_background_error().is_none());
+    }
+
+    #[tokio::test]
+    async fn channel_is_restored_from_the_store() {
+        let transport = MemoryTransport::new();
+        let store = MemoryStateStore::new();
+        let mut channel = ChannelWriter::builder().build_with_transport(transport.clone());
+        channel.open().await.unwrap();
+        let first = JsonPacketBuilder::new().public(&"first".to_string()).unwrap().build();
+        let last_msg_id = channel.send_signed_packet(&first).await.unwrap();
+        channel.persist_to(&store, "psw").await.unwrap();
+
+        let (channel_id, _) = channel.channel_address();
+        let state = ChannelState::decrypt(&store.load(&channel_id).unwrap(), "psw").unwrap();
+        assert_eq!(state.last_msg_id(), last_msg_id);
+        let mut restored = ChannelWriter::import_with_transport(&state, "psw", transport.clone()).await.unwrap();
+        let second = JsonPacketBuilder::new().public(&"second".to_string()).unwrap().build();
+        restored.send_signed_packet(&second).await.unwrap();
+        assert_eq!(transport.len(), 3);
     }
 }
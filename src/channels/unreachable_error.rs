@@ -0,0 +1,71 @@
+use std::fmt::{Display, Formatter};
+
+///
+/// Error returned by the writer when a packet has been sent but the node can't be reached to check if it has been attached.
+/// It is a transient error: the packet can be sent again when the node is reachable, after the state of the channel is updated.
+/// When the writer has an outbox the packet has been stored in it instead, and it is sent with the outbox.
+///
+#[derive(Debug)]
+pub struct UnreachableNodeError{
+    cause: anyhow::Error,
+    queued: bool,
+}
+
+impl UnreachableNodeError{
+    pub fn new(cause: anyhow::Error) -> UnreachableNodeError{
+        UnreachableNodeError{
+            cause,
+            queued: false
+        }
+    }
+
+    ///
+    /// Mark the packet of an unreachable node error as stored in the outbox
+    ///
+    pub(crate) fn into_queued(err: anyhow::Error) -> anyhow::Error{
+        match err.downcast::<UnreachableNodeError>(){
+            Ok(mut unreachable) => {
+                unreachable.queued = true;
+                unreachable.into()
+            }
+            Err(err) => err
+        }
+    }
+
+    ///
+    /// Check if the error has been caused by an unreachable node
+    ///
+    pub fn is_unreachable(err: &anyhow::Error) -> bool{
+        err.is::<UnreachableNodeError>()
+    }
+
+    ///
+    /// Check if the packet has been stored in the outbox because the node is not reachable
+    ///
+    pub fn is_queued(err: &anyhow::Error) -> bool{
+        matches!(err.downcast_ref::<UnreachableNodeError>(), Some(unreachable) if unreachable.queued)
+    }
+
+    ///
+    /// The error returned by the node
+    ///
+    pub fn cause(&self) -> &anyhow::Error {
+        &self.cause
+    }
+}
+
+impl Display for UnreachableNodeError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.queued{
+            write!(f, "The packet has been stored in the outbox, since the node is not reachable: {}", self.cause)
+        } else {
+            write!(f, "It is unknown if the packet has been attached, since the node is not reachable: {}", self.cause)
+        }
+    }
+}
+
+impl std::error::Error for UnreachableNodeError{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.cause.as_ref())
+    }
+}
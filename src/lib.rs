@@ -11,4 +11,6 @@ pub mod channels;
 pub mod payload;
 pub mod utility;
 pub mod user_builders;
+pub mod storage;
+pub mod transport;
 
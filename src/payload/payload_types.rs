@@ -6,6 +6,8 @@ use iota_streams::ddml::types::Bytes;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use crate::utility::iota_utility::{decrypt_data, encrypt_data};
+use crate::utility::secrets::SymmetricKey;
+use zeroize::{Zeroize, Zeroizing};
 
 pub trait StreamsPacketSerializer {
     fn serialize<T: Serialize>(data: &T) -> Result<String>;
@@ -16,14 +18,14 @@ pub struct StreamsPacket<P>{
     p_data: Vec<u8>,
     m_data: Vec<u8>,
     _marker: PhantomData<P>,
-    key_nonce: Option<([u8;32], [u8;24])>,
+    key_nonce: Option<SymmetricKey>,
 }
 
 impl<P> StreamsPacket<P>
 where
     P: StreamsPacketSerializer,
 {
-    fn new(p_data: &[u8], m_data: &[u8], key_nonce: Option<([u8;32], [u8;24])>) -> StreamsPacket<P>{
+    fn new(p_data: &[u8], m_data: &[u8], key_nonce: Option<SymmetricKey>) -> StreamsPacket<P>{
         StreamsPacket{
             p_data: p_data.to_vec(),
             m_data: m_data.to_vec(),
@@ -32,21 +34,22 @@ where
         }
     }
 
-    pub fn from_streams_response(p_data: &[u8], m_data: &[u8], key_nonce: &Option<([u8;32], [u8;24])>) -> Result<StreamsPacket<P>>{
-        let (p, m) = match key_nonce{
-            None => (p_data.to_vec(), m_data.to_vec()),
-            Some((key, nonce)) => {
-                let dec = decrypt_data(m_data, key, nonce)?;
-                (p_data.to_vec(), dec)
-            }
+    ///
+    /// Create the packet from the payloads of a message, decrypting the masked payload with the key and nonce if present
+    ///
+    pub fn from_streams_response(p_data: &[u8], m_data: &[u8], key: Option<&SymmetricKey>) -> Result<StreamsPacket<P>>{
+        let key_nonce = key.cloned();
+        let m = match &key_nonce{
+            None => Zeroizing::new(m_data.to_vec()),
+            Some(key) => decrypt_data(m_data, key.key(), key.nonce())?
         };
 
         Ok(
             StreamsPacket{
-            p_data: decode_config(p, URL_SAFE_NO_PAD)?,
-            m_data: decode_config(m, URL_SAFE_NO_PAD)?,
+            p_data: decode_config(p_data, URL_SAFE_NO_PAD)?,
+            m_data: decode_config(&*m, URL_SAFE_NO_PAD)?,
             _marker: PhantomData,
-            key_nonce: key_nonce.clone(),
+            key_nonce,
             }
         )
     }
@@ -57,10 +60,10 @@ where
     }
 
     pub fn masked_data(&self) -> Result<Bytes> {
-        let m = encode_config(&self.m_data, URL_SAFE_NO_PAD).as_bytes().to_vec();
+        let m = Zeroizing::new(encode_config(&self.m_data, URL_SAFE_NO_PAD).as_bytes().to_vec());
         let data = match &self.key_nonce{
-            None => m,
-            Some((key, nonce)) => encrypt_data(&m, key, nonce)?
+            None => m.to_vec(),
+            Some(key) => encrypt_data(&m, key.key(), key.nonce())?
         };
 
         Ok(Bytes(data))
@@ -90,11 +93,17 @@ where
 
 }
 
+impl<P> Drop for StreamsPacket<P>{
+    fn drop(&mut self) {
+        self.m_data.zeroize();
+    }
+}
+
 pub struct StreamsPacketBuilder<P>{
     public: String,
     masked: String,
     _pub_marker: PhantomData<P>,
-    key_nonce: Option<([u8;32], [u8;24])>,
+    key_nonce: Option<SymmetricKey>,
 }
 
 impl<P> StreamsPacketBuilder<P>
@@ -126,7 +135,12 @@ where
     }
 
     pub fn key_nonce(&mut self, key: &[u8;32], nonce: &[u8;24]) -> &mut Self{
-        self.key_nonce = Some((key.clone(), nonce.clone()));
+        self.key_nonce = Some(SymmetricKey::new(key, nonce));
+        self
+    }
+
+    pub fn key(&mut self, key: &SymmetricKey) -> &mut Self{
+        self.key_nonce = Some(key.clone());
         self
     }
 
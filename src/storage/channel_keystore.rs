@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Read;
+
+use anyhow::Result;
+use iota_streams::app::transport::tangle::client::SendOptions;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::channels::channel_state::{ChannelState, decrypt_bytes, DEFAULT_BACKUPS};
+use crate::channels::state_encryption::StateKey;
+use crate::channels::{ChannelReader, ChannelWriter};
+use crate::utility::file_utility::{atomic_write, lock_file};
+use crate::utility::secrets::SecretString;
+use crate::transport::channel_transport::ChannelTransport;
+
+#[derive(Serialize, Deserialize, Clone, Zeroize)]
+#[zeroize(drop)]
+struct KeystoreEntry{
+    label: Option<String>,
+    state: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Zeroize)]
+#[zeroize(drop)]
+struct KeystoreSnapshot{
+    entries: Vec<(String, KeystoreEntry)>,
+}
+
+///
+/// Collection of many channel states protected by a single master password.
+/// The key is derived once from the master password with the random salt of the keystore;
+/// each state, and the whole keystore when it is written in a file, is encrypted with a new random nonce.
+/// The user state inside each channel state is encrypted with the master password.
+/// The channels are identified by their address or by an optional unique label.
+///
+pub struct ChannelKeystore{
+    master_psw: SecretString,
+    key: StateKey,
+    entries: BTreeMap<String, KeystoreEntry>,
+}
+
+impl ChannelKeystore{
+    ///
+    /// Create an empty keystore protected by the master password
+    ///
+    pub fn new(master_psw: impl Into<SecretString>) -> Result<ChannelKeystore>{
+        let master_psw = master_psw.into();
+        Ok(ChannelKeystore{
+            key: StateKey::generate(master_psw.expose())?,
+            master_psw,
+            entries: BTreeMap::new(),
+        })
+    }
+
+    ///
+    /// Read a keystore from a file
+    ///
+    pub fn from_file(file_path: &str, master_psw: impl Into<SecretString>) -> Result<ChannelKeystore>{
+        let master_psw = master_psw.into();
+        let mut fr = OpenOptions::new().read(true).open(file_path)?;
+        let mut input = vec![];
+        fr.read_to_end(&mut input)?;
+        ChannelKeystore::decrypt(&input, master_psw)
+    }
+
+    ///
+    /// Decrypt a keystore. The keystores and the entries encrypted by the previous versions of the lib
+    /// are encrypted again with the key of the keystore
+    ///
+    pub fn decrypt(input: &[u8], master_psw: impl Into<SecretString>) -> Result<ChannelKeystore>{
+        let master_psw = master_psw.into();
+        let (key, dec) = match StateKey::for_envelope(input, master_psw.expose()){
+            Ok(key) => {
+                let dec = key.decrypt(input, "Error during keystore decryption")?;
+                (key, dec)
+            },
+            Err(_) => {
+                let dec = decrypt_bytes(input, master_psw.expose(), "Error during keystore decryption")?;
+                (StateKey::generate(master_psw.expose())?, dec)
+            }
+        };
+
+        let mut snapshot: KeystoreSnapshot = bincode::deserialize(&dec)?;
+        let mut keystore = ChannelKeystore{
+            master_psw,
+            key,
+            entries: BTreeMap::new(),
+        };
+        for (channel_id, entry) in snapshot.entries.drain(..){
+            let state = if keystore.key.matches(&entry.state){
+                entry.state.clone()
+            } else {
+                let channel_state = ChannelState::decrypt(&entry.state, keystore.master_psw.expose())?;
+                keystore.encrypt_state(&channel_state)?
+            };
+            keystore.entries.insert(channel_id, KeystoreEntry{ label: entry.label.clone(), state });
+        }
+        Ok(keystore)
+    }
+
+    pub fn encrypt(&self) -> Result<Vec<u8>>{
+        let snapshot = KeystoreSnapshot{
+            entries: self.entries.iter().map(|(id, entry)| (id.clone(), entry.clone())).collect()
+        };
+        let bytes = Zeroizing::new(bincode::serialize(&snapshot)?);
+        self.key.encrypt(&bytes, "Error during keystore encryption")
+    }
+
+    ///
+    /// Write the encrypted keystore in a file, keeping the previous content as backup
+    ///
+    pub fn write_to_file(&self, file_path: &str) -> Result<()>{
+        let _lock = lock_file(file_path)?;
+        atomic_write(file_path, &self.encrypt()?, DEFAULT_BACKUPS)
+    }
+}
+
+impl ChannelKeystore{
+    ///
+    /// Add the state of a writer, replacing the previous state of the same channel.
+    /// If the label is None, the label of the previous state is kept
+    ///
+    pub async fn add_writer<Trans: ChannelTransport>(&mut self, writer: &ChannelWriter<Trans>, label: Option<&str>) -> Result<()>{
+        let channel_state = writer.export(self.master_psw.expose()).await?;
+        self.add_state(&channel_state, label)
+    }
+
+    ///
+    /// Add the state of a reader, replacing the previous state of the same channel.
+    /// If the label is None, the label of the previous state is kept
+    ///
+    pub async fn add_reader<Trans: ChannelTransport>(&mut self, reader: &ChannelReader<Trans>, label: Option<&str>) -> Result<()>{
+        let channel_state = reader.export(self.master_psw.expose()).await?;
+        self.add_state(&channel_state, label)
+    }
+
+    ///
+    /// Add a channel state. Its user state must be encrypted with the master password
+    ///
+    pub fn add_state(&mut self, channel_state: &ChannelState, label: Option<&str>) -> Result<()>{
+        let state = self.encrypt_state(channel_state)?;
+        self.insert(&channel_state.channel_id(), label, state)
+    }
+
+    ///
+    /// Remove a channel, by address or label. It does nothing if the channel is not in the keystore
+    ///
+    pub fn remove(&mut self, channel: &str) -> Option<String>{
+        let channel_id = self.find(channel)?;
+        self.entries.remove(&channel_id);
+        Some(channel_id)
+    }
+
+    ///
+    /// Get the address of a channel, by address or label
+    ///
+    pub fn find(&self, channel: &str) -> Option<String>{
+        if self.entries.contains_key(channel){
+            return Some(channel.to_string());
+        }
+        self.entries.iter()
+            .find(|(_, entry)| entry.label.as_deref() == Some(channel))
+            .map(|(id, _)| id.clone())
+    }
+
+    ///
+    /// Get the state of a channel, by address or label
+    ///
+    pub fn get(&self, channel: &str) -> Result<ChannelState>{
+        let dec = self.key.decrypt(&self.encrypted_state(channel)?, "Error during keystore decryption")?;
+        ChannelState::from_bytes(&dec)
+    }
+
+    ///
+    /// Get the label of a channel, by address or label
+    ///
+    pub fn label(&self, channel: &str) -> Option<String>{
+        let channel_id = self.find(channel)?;
+        self.entries.get(&channel_id)?.label.clone()
+    }
+
+    ///
+    /// Get the addresses and labels of all the channels
+    ///
+    pub fn list(&self) -> Vec<(String, Option<String>)>{
+        self.entries.iter()
+            .map(|(id, entry)| (id.clone(), entry.label.clone()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize{
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.entries.is_empty()
+    }
+
+    ///
+    /// Restore a ChannelWriter from the keystore, by address or label
+    ///
+    pub async fn open_writer(&self, channel: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelWriter>{
+        let channel_state = self.get(channel)?;
+        ChannelWriter::import(&channel_state, self.master_psw.expose(), node_url, send_options).await
+    }
+
+    ///
+    /// Restore a ChannelReader from the keystore, by address or label
+    ///
+    pub async fn open_reader(&self, channel: &str, node_url: Option<&str>, send_options: Option<SendOptions>) -> Result<ChannelReader>{
+        let channel_state = self.get(channel)?;
+        ChannelReader::import(&channel_state, self.master_psw.expose(), node_url, send_options).await
+    }
+}
+
+impl ChannelKeystore{
+    fn insert(&mut self, channel_id: &str, label: Option<&str>, state: Vec<u8>) -> Result<()>{
+        if let Some(label) = label{
+            match self.find(label){
+                Some(other) if other != channel_id => return Err(anyhow::Error::msg(format!("The label {} is already used by the channel {}", label, other))),
+                _ => {}
+            }
+        }
+
+        let label = match label{
+            Some(label) => Some(label.to_string()),
+            None => self.entries.get(channel_id).and_then(|entry| entry.label.clone())
+        };
+        self.entries.insert(channel_id.to_string(), KeystoreEntry{ label, state });
+        Ok(())
+    }
+
+    fn encrypt_state(&self, channel_state: &ChannelState) -> Result<Vec<u8>>{
+        self.key.encrypt(&channel_state.to_bytes()?, "Error during keystore encryption")
+    }
+
+    fn encrypted_state(&self, channel: &str) -> Result<Vec<u8>>{
+        let channel_id = match self.find(channel){
+            Some(channel_id) => channel_id,
+            None => return Err(anyhow::Error::msg(format!("There is no channel {} in the keystore", channel)))
+        };
+        Ok(self.entries[&channel_id].state.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_builders::network::Network;
+
+    fn channel_state(channel_id: &str) -> ChannelState{
+        ChannelState::new(&b"user state".to_vec(), channel_id, "announcement", "last msg", &Network::Devnet)
+    }
+
+    #[test]
+    fn same_entry_encrypted_twice_differs() {
+        let mut keystore = ChannelKeystore::new("master").unwrap();
+        keystore.add_state(&channel_state("channel"), None).unwrap();
+        let first = keystore.encrypted_state("channel").unwrap();
+        keystore.add_state(&channel_state("channel"), None).unwrap();
+        let second = keystore.encrypted_state("channel").unwrap();
+
+        assert_ne!(first, second);
+        assert_ne!(keystore.encrypt().unwrap(), keystore.encrypt().unwrap());
+    }
+
+    #[test]
+    fn keystore_roundtrip_keeps_states_and_labels() {
+        let mut keystore = ChannelKeystore::new("master").unwrap();
+        keystore.add_state(&channel_state("channel"), Some("sensor")).unwrap();
+
+        let keystore = ChannelKeystore::decrypt(&keystore.encrypt().unwrap(), "master").unwrap();
+        assert_eq!(keystore.find("sensor"), Some("channel".to_string()));
+        assert_eq!(keystore.get("sensor").unwrap().last_msg_id(), "last msg");
+        assert!(ChannelKeystore::decrypt(&keystore.encrypt().unwrap(), "other").is_err());
+    }
+
+    #[test]
+    fn legacy_entries_are_encrypted_again() {
+        let legacy_entry = KeystoreEntry{ label: None, state: channel_state("channel").encrypt("master").unwrap() };
+        let snapshot = KeystoreSnapshot{ entries: vec![("channel".to_string(), legacy_entry)] };
+        let input = crate::channels::channel_state::encrypt_bytes(&bincode::serialize(&snapshot).unwrap(), "master", "err").unwrap();
+
+        let keystore = ChannelKeystore::decrypt(&input, "master").unwrap();
+        assert!(keystore.key.matches(&keystore.encrypted_state("channel").unwrap()));
+        assert_eq!(keystore.get("channel").unwrap().channel_id(), "channel");
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::storage::state_store::{StateStore, not_found};
+use crate::utility::file_utility::{atomic_write, lock_file, remove_with_backups};
+use crate::channels::channel_state::DEFAULT_BACKUPS;
+
+const STATE_EXTENSION: &str = "state";
+
+///
+/// Store that keeps each channel state in a `<channel_id>.state` file of a directory
+///
+pub struct FileStateStore{
+    dir: PathBuf,
+    backups: usize,
+}
+
+impl FileStateStore{
+    ///
+    /// Open the store in the specified directory, creating it if it doesn't exist
+    ///
+    pub fn new(dir: &str) -> Result<FileStateStore>{
+        fs::create_dir_all(dir)?;
+        Ok(FileStateStore{
+            dir: PathBuf::from(dir),
+            backups: DEFAULT_BACKUPS,
+        })
+    }
+
+    ///
+    /// Set the number of backup generations kept for each state
+    ///
+    pub fn backups(mut self, backups: usize) -> Self{
+        self.backups = backups;
+        self
+    }
+
+    fn state_path(&self, channel_id: &str) -> Result<PathBuf>{
+        if channel_id.is_empty() || !channel_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'){
+            return Err(anyhow::Error::msg(format!("Invalid channel id: {}", channel_id)));
+        }
+        Ok(self.dir.join(format!("{}.{}", channel_id, STATE_EXTENSION)))
+    }
+}
+
+impl StateStore for FileStateStore{
+    fn save(&self, channel_id: &str, state: &[u8]) -> Result<()> {
+        let path = self.state_path(channel_id)?;
+        let path = path.to_string_lossy();
+        let _lock = lock_file(&path)?;
+        atomic_write(&path, state, self.backups)
+    }
+
+    fn load(&self, channel_id: &str) -> Result<Vec<u8>> {
+        match fs::read(self.state_path(channel_id)?){
+            Ok(state) => Ok(state),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(not_found(channel_id)),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut res = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(STATE_EXTENSION){
+                continue;
+            }
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()){
+                res.push(id.to_string());
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+
+    fn delete(&self, channel_id: &str) -> Result<()> {
+        let path = self.state_path(channel_id)?;
+        remove_with_backups(&path.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::state_store::tests::check_store;
+
+    #[test]
+    fn file_store_keeps_the_states() {
+        let dir = tempfile::tempdir().unwrap();
+        check_store(&FileStateStore::new(dir.path().to_str().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn channel_id_must_be_a_file_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStateStore::new(dir.path().join("states").to_str().unwrap()).unwrap();
+        assert!(store.save("../channel", b"state").is_err());
+        assert!(store.save("", b"state").is_err());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn delete_removes_backups_and_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStateStore::new(dir.path().to_str().unwrap()).unwrap().backups(2);
+        store.save("channel", b"first").unwrap();
+        store.save("channel", b"second").unwrap();
+        store.save("other", b"other").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["channel".to_string(), "other".to_string()]);
+
+        store.delete("channel").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["other".to_string()]);
+        let names: Vec<String> = fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(names.iter().all(|name| !name.starts_with("channel")));
+        assert!(store.load("channel").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use anyhow::Result;
+
+use crate::storage::mirror_store::{MirrorStore, MirroredMsg};
+
+///
+/// Mirror store that keeps the messages in memory. The messages are lost when the store is dropped
+///
+pub struct MemoryMirrorStore{
+    channels: RwLock<HashMap<String, Vec<MirroredMsg>>>,
+}
+
+impl MemoryMirrorStore{
+    pub fn new() -> MemoryMirrorStore{
+        MemoryMirrorStore{
+            channels: RwLock::new(HashMap::new())
+        }
+    }
+}
+
+impl Default for MemoryMirrorStore{
+    fn default() -> Self {
+        MemoryMirrorStore::new()
+    }
+}
+
+impl MirrorStore for MemoryMirrorStore{
+    fn insert(&self, msg: &MirroredMsg) -> Result<bool> {
+        let mut channels = match self.channels.write(){
+            Ok(channels) => channels,
+            Err(_) => return Err(anyhow::Error::msg("Mirror store is poisoned"))
+        };
+        let msgs = channels.entry(msg.channel_id.clone()).or_insert_with(Vec::new);
+        if msgs.iter().any(|m| m.msg_id == msg.msg_id){
+            return Ok(false);
+        }
+        msgs.push(msg.clone());
+        Ok(true)
+    }
+
+    fn get(&self, channel_id: &str, msg_id: &str) -> Result<Option<MirroredMsg>> {
+        let channels = match self.channels.read(){
+            Ok(channels) => channels,
+            Err(_) => return Err(anyhow::Error::msg("Mirror store is poisoned"))
+        };
+        Ok(channels.get(channel_id).and_then(|msgs| msgs.iter().find(|m| m.msg_id == msg_id).cloned()))
+    }
+
+    fn last(&self, channel_id: &str) -> Result<Option<MirroredMsg>> {
+        let channels = match self.channels.read(){
+            Ok(channels) => channels,
+            Err(_) => return Err(anyhow::Error::msg("Mirror store is poisoned"))
+        };
+        Ok(channels.get(channel_id).and_then(|msgs| msgs.last().cloned()))
+    }
+
+    fn messages(&self, channel_id: &str) -> Result<Vec<MirroredMsg>> {
+        let channels = match self.channels.read(){
+            Ok(channels) => channels,
+            Err(_) => return Err(anyhow::Error::msg("Mirror store is poisoned"))
+        };
+        Ok(channels.get(channel_id).cloned().unwrap_or_default())
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use anyhow::Result;
+
+use crate::storage::state_store::{StateStore, not_found};
+
+///
+/// Store that keeps the channel states in memory. The states are lost when the store is dropped
+///
+pub struct MemoryStateStore{
+    states: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStateStore{
+    pub fn new() -> MemoryStateStore{
+        MemoryStateStore{
+            states: RwLock::new(HashMap::new())
+        }
+    }
+}
+
+impl Default for MemoryStateStore{
+    fn default() -> Self {
+        MemoryStateStore::new()
+    }
+}
+
+impl StateStore for MemoryStateStore{
+    fn save(&self, channel_id: &str, state: &[u8]) -> Result<()> {
+        let mut states = match self.states.write(){
+            Ok(states) => states,
+            Err(_) => return Err(anyhow::Error::msg("State store is poisoned"))
+        };
+        states.insert(channel_id.to_string(), state.to_vec());
+        Ok(())
+    }
+
+    fn load(&self, channel_id: &str) -> Result<Vec<u8>> {
+        let states = match self.states.read(){
+            Ok(states) => states,
+            Err(_) => return Err(anyhow::Error::msg("State store is poisoned"))
+        };
+        match states.get(channel_id){
+            Some(state) => Ok(state.clone()),
+            None => Err(not_found(channel_id))
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let states = match self.states.read(){
+            Ok(states) => states,
+            Err(_) => return Err(anyhow::Error::msg("State store is poisoned"))
+        };
+        let mut res: Vec<String> = states.keys().cloned().collect();
+        res.sort();
+        Ok(res)
+    }
+
+    fn delete(&self, channel_id: &str) -> Result<()> {
+        let mut states = match self.states.write(){
+            Ok(states) => states,
+            Err(_) => return Err(anyhow::Error::msg("State store is poisoned"))
+        };
+        states.remove(channel_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::state_store::tests::check_store;
+
+    #[test]
+    fn memory_store_keeps_the_states() {
+        check_store(&MemoryStateStore::new());
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+///
+/// Message of a channel mirrored in a local store.
+/// `seq` is the position of the message in the mirror and `prev_msg_id` is the id of the message it is linked to in the channel,
+/// None for the messages restored from states that didn't record it
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MirroredMsg{
+    pub channel_id: String,
+    pub msg_id: String,
+    pub seq: u64,
+    pub prev_msg_id: Option<String>,
+    pub publisher: String,
+    pub public: Vec<u8>,
+    pub masked: Vec<u8>,
+    pub fetched_at: u64,
+}
+
+///
+/// Storage of the messages mirrored from the channels, identified by the channel id and the msg id
+///
+pub trait MirrorStore: Send + Sync {
+    ///
+    /// Store a message. It returns false, without changing anything, if the message is already stored
+    ///
+    fn insert(&self, msg: &MirroredMsg) -> Result<bool>;
+
+    ///
+    /// Get a message of a channel by its id
+    ///
+    fn get(&self, channel_id: &str, msg_id: &str) -> Result<Option<MirroredMsg>>;
+
+    ///
+    /// Get the last mirrored message of a channel
+    ///
+    fn last(&self, channel_id: &str) -> Result<Option<MirroredMsg>>;
+
+    ///
+    /// Get all the messages of a channel, in the order of the chain
+    ///
+    fn messages(&self, channel_id: &str) -> Result<Vec<MirroredMsg>>;
+
+    ///
+    /// Get the messages of a channel signed by the publisher with the specified hex encoded public key
+    ///
+    fn messages_by_publisher(&self, channel_id: &str, publisher: &str) -> Result<Vec<MirroredMsg>>{
+        Ok(self.messages(channel_id)?
+            .into_iter()
+            .filter(|msg| msg.publisher == publisher)
+            .collect())
+    }
+}
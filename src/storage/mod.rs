@@ -0,0 +1,10 @@
+pub mod state_store;
+pub mod file_state_store;
+pub mod memory_state_store;
+pub mod channel_keystore;
+pub mod mirror_store;
+pub mod memory_mirror_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_state_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_mirror_store;
@@ -0,0 +1,128 @@
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::storage::mirror_store::{MirrorStore, MirroredMsg};
+
+const MSG_COLUMNS: &str = "channel_id, msg_id, seq, prev_msg_id, publisher, public, masked, fetched_at";
+
+///
+/// Mirror store that keeps the messages in a table of a SQLite database, so they can be queried offline
+///
+pub struct SqliteMirrorStore{
+    conn: Mutex<Connection>,
+}
+
+impl SqliteMirrorStore{
+    ///
+    /// Open the database at the specified path, creating the table of the messages if needed
+    ///
+    pub fn open(db_path: &str) -> Result<SqliteMirrorStore>{
+        SqliteMirrorStore::from_connection(Connection::open(db_path)?)
+    }
+
+    ///
+    /// Open a database that lives only in memory
+    ///
+    pub fn open_in_memory() -> Result<SqliteMirrorStore>{
+        SqliteMirrorStore::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<SqliteMirrorStore>{
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS channel_msgs (
+                channel_id TEXT NOT NULL,
+                msg_id TEXT NOT NULL,
+                seq INTEGER NOT NULL,
+                prev_msg_id TEXT,
+                publisher TEXT NOT NULL,
+                public BLOB NOT NULL,
+                masked BLOB NOT NULL,
+                fetched_at INTEGER NOT NULL,
+                PRIMARY KEY (channel_id, msg_id)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS channel_msgs_seq ON channel_msgs (channel_id, seq)",
+            [],
+        )?;
+        Ok(SqliteMirrorStore{
+            conn: Mutex::new(conn)
+        })
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>>{
+        match self.conn.lock(){
+            Ok(conn) => Ok(conn),
+            Err(_) => Err(anyhow::Error::msg("Mirror store is poisoned"))
+        }
+    }
+}
+
+fn read_msg(row: &Row) -> rusqlite::Result<MirroredMsg>{
+    Ok(MirroredMsg{
+        channel_id: row.get(0)?,
+        msg_id: row.get(1)?,
+        seq: row.get::<_, i64>(2)? as u64,
+        prev_msg_id: row.get(3)?,
+        publisher: row.get(4)?,
+        public: row.get(5)?,
+        masked: row.get(6)?,
+        fetched_at: row.get::<_, i64>(7)? as u64,
+    })
+}
+
+impl MirrorStore for SqliteMirrorStore{
+    fn insert(&self, msg: &MirroredMsg) -> Result<bool> {
+        let inserted = self.conn()?.execute(
+            &format!("INSERT OR IGNORE INTO channel_msgs ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", MSG_COLUMNS),
+            params![
+                msg.channel_id,
+                msg.msg_id,
+                msg.seq as i64,
+                msg.prev_msg_id,
+                msg.publisher,
+                msg.public,
+                msg.masked,
+                msg.fetched_at as i64
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    fn get(&self, channel_id: &str, msg_id: &str) -> Result<Option<MirroredMsg>> {
+        let msg = self.conn()?.query_row(
+            &format!("SELECT {} FROM channel_msgs WHERE channel_id = ?1 AND msg_id = ?2", MSG_COLUMNS),
+            params![channel_id, msg_id],
+            read_msg,
+        ).optional()?;
+        Ok(msg)
+    }
+
+    fn last(&self, channel_id: &str) -> Result<Option<MirroredMsg>> {
+        let msg = self.conn()?.query_row(
+            &format!("SELECT {} FROM channel_msgs WHERE channel_id = ?1 ORDER BY seq DESC LIMIT 1", MSG_COLUMNS),
+            params![channel_id],
+            read_msg,
+        ).optional()?;
+        Ok(msg)
+    }
+
+    fn messages(&self, channel_id: &str) -> Result<Vec<MirroredMsg>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM channel_msgs WHERE channel_id = ?1 ORDER BY seq", MSG_COLUMNS))?;
+        let msgs = stmt.query_map(params![channel_id], read_msg)?
+            .collect::<std::result::Result<Vec<MirroredMsg>, _>>()?;
+        Ok(msgs)
+    }
+
+    fn messages_by_publisher(&self, channel_id: &str, publisher: &str) -> Result<Vec<MirroredMsg>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM channel_msgs WHERE channel_id = ?1 AND publisher = ?2 ORDER BY seq", MSG_COLUMNS))?;
+        let msgs = stmt.query_map(params![channel_id, publisher], read_msg)?
+            .collect::<std::result::Result<Vec<MirroredMsg>, _>>()?;
+        Ok(msgs)
+    }
+}
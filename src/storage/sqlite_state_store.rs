@@ -0,0 +1,108 @@
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::storage::state_store::{StateStore, not_found};
+
+///
+/// Store that keeps the channel states in a table of a SQLite database
+///
+pub struct SqliteStateStore{
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStateStore{
+    ///
+    /// Open the database at the specified path, creating the table of the states if needed
+    ///
+    pub fn open(db_path: &str) -> Result<SqliteStateStore>{
+        SqliteStateStore::from_connection(Connection::open(db_path)?)
+    }
+
+    ///
+    /// Open a database that lives only in memory
+    ///
+    pub fn open_in_memory() -> Result<SqliteStateStore>{
+        SqliteStateStore::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<SqliteStateStore>{
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS channel_states (
+                channel_id TEXT PRIMARY KEY,
+                state BLOB NOT NULL
+            )",
+            [],
+        )?;
+        Ok(SqliteStateStore{
+            conn: Mutex::new(conn)
+        })
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>>{
+        match self.conn.lock(){
+            Ok(conn) => Ok(conn),
+            Err(_) => Err(anyhow::Error::msg("State store is poisoned"))
+        }
+    }
+}
+
+impl StateStore for SqliteStateStore{
+    fn save(&self, channel_id: &str, state: &[u8]) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO channel_states (channel_id, state) VALUES (?1, ?2)
+             ON CONFLICT(channel_id) DO UPDATE SET state = excluded.state",
+            params![channel_id, state],
+        )?;
+        Ok(())
+    }
+
+    fn load(&self, channel_id: &str) -> Result<Vec<u8>> {
+        let state: Option<Vec<u8>> = self.conn()?.query_row(
+            "SELECT state FROM channel_states WHERE channel_id = ?1",
+            params![channel_id],
+            |row| row.get(0),
+        ).optional()?;
+        match state{
+            Some(state) => Ok(state),
+            None => Err(not_found(channel_id))
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT channel_id FROM channel_states ORDER BY channel_id")?;
+        let ids = stmt.query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(ids)
+    }
+
+    fn delete(&self, channel_id: &str) -> Result<()> {
+        self.conn()?.execute(
+            "DELETE FROM channel_states WHERE channel_id = ?1",
+            params![channel_id],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::state_store::tests::check_store;
+
+    #[test]
+    fn sqlite_store_keeps_the_states() {
+        check_store(&SqliteStateStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn states_are_kept_in_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("states.db");
+        SqliteStateStore::open(db_path.to_str().unwrap()).unwrap().save("channel", b"state").unwrap();
+        let store = SqliteStateStore::open(db_path.to_str().unwrap()).unwrap();
+        assert_eq!(store.load("channel").unwrap(), b"state".to_vec());
+    }
+}
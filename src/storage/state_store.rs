@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+
+///
+/// Storage of the encrypted channel states, identified by the channel id
+///
+pub trait StateStore: Send + Sync {
+    ///
+    /// Store the encrypted state of a channel, replacing the previous one
+    ///
+    fn save(&self, channel_id: &str, state: &[u8]) -> Result<()>;
+
+    ///
+    /// Get the encrypted state of a channel. If the channel is not stored the error is a `StateNotFoundError`
+    ///
+    fn load(&self, channel_id: &str) -> Result<Vec<u8>>;
+
+    ///
+    /// Get the ids of all the stored channels
+    ///
+    fn list(&self) -> Result<Vec<String>>;
+
+    ///
+    /// Remove the state of a channel. It does nothing if the channel is not stored
+    ///
+    fn delete(&self, channel_id: &str) -> Result<()>;
+}
+
+///
+/// Error returned by the stores when there is no state for a channel
+///
+#[derive(Debug)]
+pub struct StateNotFoundError{
+    channel_id: String,
+}
+
+impl StateNotFoundError{
+    ///
+    /// Check if the error has been caused by a channel that is not stored
+    ///
+    pub fn is_not_found(err: &anyhow::Error) -> bool{
+        err.is::<StateNotFoundError>()
+    }
+}
+
+impl Display for StateNotFoundError{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "There is no state for the channel {}", self.channel_id)
+    }
+}
+
+impl std::error::Error for StateNotFoundError{}
+
+pub(crate) fn not_found(channel_id: &str) -> anyhow::Error{
+    StateNotFoundError{ channel_id: channel_id.to_string() }.into()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    ///
+    /// Check the behaviour shared by all the stores
+    ///
+    pub(crate) fn check_store(store: &dyn StateStore){
+        assert!(store.list().unwrap().is_empty());
+        assert!(StateNotFoundError::is_not_found(&store.load("channel").unwrap_err()));
+
+        store.save("channel", b"first").unwrap();
+        store.save("channel", b"second").unwrap();
+        store.save("another", b"another").unwrap();
+        assert_eq!(store.load("channel").unwrap(), b"second".to_vec());
+        assert_eq!(store.list().unwrap(), vec!["another".to_string(), "channel".to_string()]);
+
+        store.delete("channel").unwrap();
+        store.delete("missing").unwrap();
+        assert!(StateNotFoundError::is_not_found(&store.load("channel").unwrap_err()));
+        assert_eq!(store.list().unwrap(), vec!["another".to_string()]);
+    }
+}
@@ -0,0 +1,33 @@
+pub use iota_streams::app::transport::tangle::client::Client as StreamsClient;
+use iota_streams::app_channels::api::tangle::Transport;
+
+use crate::user_builders::node_pool::NodePool;
+use crate::utility::iota_utility::create_link;
+
+///
+/// Transport used by the channels to attach and fetch the messages
+///
+pub trait ChannelTransport: Transport + Clone + Sized{
+    ///
+    /// Create a transport connected to the current node of the pool, used after a failover.
+    /// The transports that don't use a node return None, so the channel never switches transport
+    ///
+    fn connect(node_pool: &NodePool) -> Option<Self>;
+}
+
+impl ChannelTransport for StreamsClient{
+    fn connect(node_pool: &NodePool) -> Option<Self> {
+        Some(node_pool.client())
+    }
+}
+
+///
+/// Check if the announcement of a channel can be received through the transport.
+/// The fetch methods of the users don't return the errors, so this tells an empty fetch from an unreachable node
+///
+pub(crate) async fn announcement_reachable<Trans: ChannelTransport>(mut transport: Trans, channel_address: &str, announcement_id: &str) -> bool{
+    match create_link(channel_address, announcement_id){
+        Ok(link) => transport.recv_message(&link).await.is_ok(),
+        Err(_) => false
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use iota_streams::app::transport::{Transport, TransportDetails, TransportOptions};
+use iota_streams::app_channels::api::tangle::{Address, Message as TangleMessage};
+
+use crate::channels::ProofMsg;
+use crate::transport::channel_transport::ChannelTransport;
+use crate::user_builders::node_pool::NodePool;
+use crate::utility::file_utility::atomic_write;
+
+///
+/// Tangle kept in memory, to use the channels without a node in tests and local tools.
+/// The clones of a MemoryTransport share the same messages, so a writer and its readers see each other
+///
+#[derive(Clone, Default)]
+pub struct MemoryTransport{
+    tangle: Arc<Mutex<MemoryTangle>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MemoryTangle{
+    msgs: BTreeMap<String, Vec<ProofMsg>>,
+    #[serde(skip)]
+    offline: bool,
+    #[serde(skip)]
+    lose_answers: bool,
+}
+
+impl MemoryTransport{
+    pub fn new() -> MemoryTransport{
+        MemoryTransport::default()
+    }
+
+    ///
+    /// Load the messages saved with `save`. If the file doesn't exist the tangle is empty
+    ///
+    pub fn load(file_path: &str) -> Result<MemoryTransport>{
+        if !Path::new(file_path).exists(){
+            return Ok(MemoryTransport::new());
+        }
+        let tangle: MemoryTangle = serde_json::from_slice(&fs::read(file_path)?)?;
+        Ok(MemoryTransport{ tangle: Arc::new(Mutex::new(tangle)) })
+    }
+
+    ///
+    /// Save all the messages in a file
+    ///
+    pub fn save(&self, file_path: &str) -> Result<()>{
+        let data = serde_json::to_vec(&*self.tangle())?;
+        atomic_write(file_path, &data, 0)
+    }
+
+    ///
+    /// Simulate a node that is not reachable: while offline every send and fetch fails
+    ///
+    pub fn set_online(&self, online: bool){
+        self.tangle().offline = !online;
+    }
+
+    pub fn is_online(&self) -> bool{
+        !self.tangle().offline
+    }
+
+    ///
+    /// Simulate a node that attaches the messages but whose answer is lost, so the sends fail after the attach
+    ///
+    pub fn set_lose_answers(&self, lose_answers: bool){
+        self.tangle().lose_answers = lose_answers;
+    }
+
+    ///
+    /// Get the number of messages attached
+    ///
+    pub fn len(&self) -> usize{
+        self.tangle().msgs.values().map(|msgs| msgs.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+}
+
+impl MemoryTransport{
+    // A panic while the lock is held can't leave the map half updated, so the poisoned lock is still usable
+    fn tangle(&self) -> MutexGuard<'_, MemoryTangle>{
+        match self.tangle.lock(){
+            Ok(tangle) => tangle,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+
+    fn check_online(&self) -> Result<()>{
+        match self.is_online(){
+            true => Ok(()),
+            false => Err(anyhow::Error::msg("The memory tangle is offline"))
+        }
+    }
+}
+
+fn link_key(link: &Address) -> String{
+    format!("{}:{}", link.appinst, link.msgid)
+}
+
+#[async_trait(?Send)]
+impl Transport<Address, TangleMessage> for MemoryTransport{
+    async fn send_message(&mut self, msg: &TangleMessage) -> Result<()> {
+        self.check_online()?;
+        let msg = ProofMsg::from_message(msg);
+        let mut tangle = self.tangle();
+        tangle.msgs.entry(msg.link.clone()).or_insert_with(Vec::new).push(msg);
+        match tangle.lose_answers{
+            true => Err(anyhow::Error::msg("The answer of the memory tangle has been lost")),
+            false => Ok(())
+        }
+    }
+
+    async fn recv_messages(&mut self, link: &Address) -> Result<Vec<TangleMessage>> {
+        self.check_online()?;
+        let msgs = match self.tangle().msgs.get(&link_key(link)){
+            Some(msgs) => msgs.clone(),
+            None => vec![]
+        };
+        if msgs.is_empty(){
+            return Err(anyhow::Error::msg(format!("There is no message {} in the memory tangle", link_key(link))));
+        }
+        msgs.iter().map(|msg| msg.to_message()).collect()
+    }
+}
+
+impl TransportOptions for MemoryTransport{
+    type SendOptions = ();
+    fn get_send_options(&self) -> Self::SendOptions {}
+    fn set_send_options(&mut self, _opt: Self::SendOptions) {}
+
+    type RecvOptions = ();
+    fn get_recv_options(&self) -> Self::RecvOptions {}
+    fn set_recv_options(&mut self, _opt: Self::RecvOptions) {}
+}
+
+#[async_trait(?Send)]
+impl TransportDetails<Address> for MemoryTransport{
+    type Details = ();
+    async fn get_link_details(&mut self, _link: &Address) -> Result<Self::Details> {
+        Ok(())
+    }
+}
+
+impl ChannelTransport for MemoryTransport{
+    fn connect(_node_pool: &NodePool) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::{ChannelReader, ChannelWriter};
+    use crate::payload::payload_serializers::JsonPacketBuilder;
+
+    async fn open_channel(transport: &MemoryTransport) -> ChannelWriter<MemoryTransport>{
+        let mut channel = ChannelWriter::builder().build_with_transport(transport.clone());
+        channel.open().await.unwrap();
+        channel
+    }
+
+    #[tokio::test]
+    async fn reader_receives_the_packets_of_the_writer() {
+        let transport = MemoryTransport::new();
+        let mut channel = open_channel(&transport).await;
+        let packet = JsonPacketBuilder::new().public(&"hello".to_string()).unwrap().build();
+        let msg_id = channel.send_signed_packet(&packet).await.unwrap();
+
+        let (channel_id, announce_id) = channel.channel_address();
+        let mut reader = ChannelReader::builder().build_with_transport(&channel_id, &announce_id, transport.clone());
+        reader.attach().await.unwrap();
+        let msgs = reader.fetch_raw_msgs().await;
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].0, msg_id);
+    }
+
+    #[tokio::test]
+    async fn saved_tangle_is_loaded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("tangle.json");
+        let file_path = file_path.to_str().unwrap();
+        let transport = MemoryTransport::new();
+        open_channel(&transport).await;
+        transport.save(file_path).unwrap();
+
+        let loaded = MemoryTransport::load(file_path).unwrap();
+        assert_eq!(loaded.len(), transport.len());
+        assert!(!loaded.is_empty());
+        assert!(MemoryTransport::load(dir.path().join("missing.json").to_str().unwrap()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn offline_tangle_rejects_the_messages() {
+        let transport = MemoryTransport::new();
+        let mut channel = ChannelWriter::builder().build_with_transport(transport.clone());
+        transport.set_online(false);
+        assert!(channel.open().await.is_err());
+        assert!(transport.is_empty());
+
+        transport.set_online(true);
+        assert!(channel.open().await.is_ok());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod channel_transport;
+pub mod memory_transport;
@@ -1,52 +1,38 @@
-use iota_streams::app::transport::{
-    TransportOptions,
-    tangle::client::{Client as StreamsClient, SendOptions}
-};
+use iota_streams::app::transport::tangle::client::{Client as StreamsClient, SendOptions};
 use iota_streams::app_channels::api::tangle::Author;
-use crate::utility::iota_utility::{random_seed, hash_string};
+use crate::utility::iota_utility::{random_seed, hash_string, mnemonic_to_seed};
 use anyhow::Result;
 use iota_streams::app_channels::api::ChannelType;
+use crate::user_builders::node_pool::{NodePool, FailoverStrategy};
+use crate::user_builders::network::Network;
+use crate::utility::secrets::SecretString;
+use crate::transport::channel_transport::ChannelTransport;
 
 pub struct AuthorBuilder{
     seed: String,
-    node_url: String,
-    send_options: SendOptions
+    node_pool: NodePool,
+    channel_type: ChannelType,
 }
 
 impl AuthorBuilder{
     pub fn new() -> AuthorBuilder{
-        let mut send_opts = SendOptions::default();
-        send_opts.local_pow = false;
-
         AuthorBuilder{
             seed: random_seed(),
-            node_url: "https://api.lb-0.testnet.chrysalis2.com".to_string(),
-            send_options: send_opts
+            node_pool: NodePool::default(),
+            channel_type: ChannelType::SingleBranch,
         }
     }
 
     pub async fn build_from_state(author_state: &[u8],
                             psw: &str,
+                            network: &Network,
                             node_url: Option<&str>,
                             send_option: Option<SendOptions>) -> Result<Author<StreamsClient>>{
 
-        let psw_hash = hash_string(psw);
-        let node = match node_url {
-            Some(url) => url,
-            None => "https://api.lb-0.testnet.chrysalis2.com"
-        };
-        let options = match send_option {
-            Some(so) => so,
-            None => {
-                let mut s = SendOptions::default();
-                s.local_pow = false;
-                s
-            }
-        };
-
-        let mut client = StreamsClient::new_from_url(&node);
-        client.set_send_options(options);
-        Author::import(author_state, &psw_hash, client).await
+        let psw_hash = SecretString::from(hash_string(psw));
+        let pool = NodePool::from_network(network).override_with(node_url, send_option);
+
+        Author::import(author_state, psw_hash.expose(), pool.client()).await
     }
 }
 
@@ -56,24 +42,78 @@ impl AuthorBuilder{
         self
     }
 
-    pub fn node(mut self, node_url: &str) -> Self{
-        self.node_url = node_url.to_string();
+    ///
+    /// Use the seed derived from a BIP39 mnemonic, with an optional passphrase
+    ///
+    pub fn mnemonic(mut self, mnemonic: &str, passphrase: Option<&str>) -> anyhow::Result<Self>{
+        self.seed = mnemonic_to_seed(mnemonic, passphrase)?;
+        Ok(self)
+    }
+
+    pub fn node(self, node_url: &str) -> Self{
+        self.nodes(&[node_url])
+    }
+
+    ///
+    /// Set the list of nodes: the author is connected to the first one, the others are used for failover
+    ///
+    pub fn nodes(mut self, node_urls: &[&str]) -> Self{
+        self.node_pool = self.node_pool.with_nodes(node_urls);
+        self
+    }
+
+    ///
+    /// Use the default nodes and send options of the specified network
+    ///
+    pub fn network(mut self, network: Network) -> Self{
+        self.node_pool = NodePool::from_network(&network)
+            .strategy(self.node_pool.failover_strategy());
+        self
+    }
+
+    pub fn failover_strategy(mut self, strategy: FailoverStrategy) -> Self{
+        self.node_pool = self.node_pool.strategy(strategy);
+        self
+    }
+
+    pub fn channel_type(mut self, channel_type: ChannelType) -> Self{
+        self.channel_type = channel_type;
         self
     }
 
     pub fn send_options(mut self, send_options: SendOptions) -> Self{
-        self.send_options = send_options;
+        self.node_pool = self.node_pool.send_options(send_options);
         self
     }
 
+    ///
+    /// Name of the channel type, recorded in the exported states
+    ///
+    pub fn channel_type_name(&self) -> &'static str{
+        match self.channel_type{
+            ChannelType::SingleBranch => "single-branch",
+            ChannelType::MultiBranch => "multi-branch",
+            ChannelType::SingleDepth => "single-depth",
+        }
+    }
+
+    pub fn node_pool(&self) -> NodePool{
+        self.node_pool.clone()
+    }
+
     pub fn build(self) -> Author<StreamsClient>{
-        let mut client = StreamsClient::new_from_url(&self.node_url);
-        client.set_send_options(self.send_options);
+        let client = self.node_pool.client();
+        self.build_with_transport(client)
+    }
 
+    ///
+    /// Build the author on the specified transport instead of a client connected to the nodes
+    ///
+    pub fn build_with_transport<Trans: ChannelTransport>(self, transport: Trans) -> Author<Trans>{
         Author::new(
             &self.seed,
-            ChannelType::SingleBranch,
-            client
+            self.channel_type,
+            transport
         )
     }
 }
@@ -1,2 +1,4 @@
 pub mod author_builder;
 pub mod subscriber_builder;
+pub mod node_pool;
+pub mod network;
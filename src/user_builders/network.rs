@@ -0,0 +1,60 @@
+use iota_streams::app::transport::tangle::client::SendOptions;
+use serde::{Deserialize, Serialize};
+
+///
+/// Tangle networks with their default nodes and send options
+///
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Network{
+    Mainnet,
+    Testnet,
+    Devnet,
+    ///
+    /// Network reachable through the specified node url
+    ///
+    Custom(String),
+    ///
+    /// Private tangle of a node running on the local machine
+    ///
+    Local,
+}
+
+impl Network{
+    pub fn node_urls(&self) -> Vec<String>{
+        let urls: &[&str] = match self{
+            Network::Mainnet => &["https://chrysalis-nodes.iota.org", "https://chrysalis-nodes.iota.cafe"],
+            Network::Testnet => &["https://api.lb-0.testnet.chrysalis2.com"],
+            Network::Devnet => &["https://api.lb-0.h.chrysalis-devnet.iota.cafe"],
+            Network::Custom(url) => return vec![url.clone()],
+            Network::Local => &["http://localhost:14265"],
+        };
+        urls.iter().map(|url| url.to_string()).collect()
+    }
+
+    ///
+    /// Get the default send options: public mainnet nodes don't do the PoW, so it is performed locally
+    ///
+    pub fn send_options(&self) -> SendOptions{
+        let mut send_opts = SendOptions::default();
+        match self{
+            Network::Mainnet => {
+                send_opts.local_pow = true;
+                send_opts.min_weight_magnitude = 14;
+            }
+            Network::Local => {
+                send_opts.local_pow = false;
+                send_opts.min_weight_magnitude = 9;
+            }
+            _ => {
+                send_opts.local_pow = false;
+            }
+        }
+        send_opts
+    }
+}
+
+impl Default for Network{
+    fn default() -> Self {
+        Network::Testnet
+    }
+}
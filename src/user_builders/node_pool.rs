@@ -0,0 +1,267 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use iota_streams::app::transport::{
+    TransportOptions,
+    tangle::client::{Client as StreamsClient, SendOptions}
+};
+use crate::user_builders::network::Network;
+
+///
+/// Strategy used to choose the node to use when the current one stops working
+///
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum FailoverStrategy{
+    ///
+    /// Move to the next healthy node of the list
+    ///
+    RoundRobin,
+    ///
+    /// The first node of the list is the primary node: the channel moves to the first healthy backup node
+    /// and goes back to the primary one when it is healthy again, checked at most once per cooldown
+    ///
+    PrimaryBackup,
+}
+
+///
+/// List of nodes of a network with the send options used to create the clients connected to them
+///
+#[derive(Clone)]
+pub struct NodePool{
+    network: Network,
+    nodes: Vec<String>,
+    current: usize,
+    strategy: FailoverStrategy,
+    send_options: SendOptions,
+    primary_cooldown: Duration,
+    last_primary_check: Option<Instant>,
+}
+
+impl NodePool{
+    pub fn new(nodes: &[&str], strategy: FailoverStrategy) -> NodePool{
+        NodePool::from_network(&Network::default())
+            .with_nodes(nodes)
+            .strategy(strategy)
+    }
+
+    ///
+    /// Create the pool with the default nodes and send options of the network
+    ///
+    pub fn from_network(network: &Network) -> NodePool{
+        NodePool{
+            network: network.clone(),
+            nodes: network.node_urls(),
+            current: 0,
+            strategy: FailoverStrategy::RoundRobin,
+            send_options: network.send_options(),
+            primary_cooldown: Duration::from_secs(60),
+            last_primary_check: None,
+        }
+    }
+
+    ///
+    /// Replace the nodes of the pool with the specified node and its send options, when present
+    ///
+    pub fn override_with(mut self, node_url: Option<&str>, send_options: Option<SendOptions>) -> Self{
+        if let Some(url) = node_url{
+            self = self.with_nodes(&[url]);
+        }
+        if let Some(so) = send_options{
+            self = self.send_options(so);
+        }
+        self
+    }
+
+    ///
+    /// Check if a node is reachable and synced using its `/health` endpoint
+    ///
+    pub async fn is_healthy(node_url: &str) -> bool{
+        let client = match reqwest::Client::builder().timeout(Duration::from_secs(5)).build(){
+            Ok(client) => client,
+            Err(_) => return false
+        };
+        let url = format!("{}/health", node_url.trim_end_matches('/'));
+        match client.get(&url).send().await{
+            Ok(res) => res.status().is_success(),
+            Err(_) => false
+        }
+    }
+
+    ///
+    /// Get the list of nodes that are currently healthy
+    ///
+    pub async fn healthy_nodes(&self) -> Vec<String>{
+        let mut res = vec![];
+        for node in &self.nodes {
+            if NodePool::is_healthy(node).await{
+                res.push(node.clone());
+            }
+        }
+        res
+    }
+}
+
+impl NodePool{
+    ///
+    /// Replace the nodes of the pool. The network and its send options are kept
+    ///
+    pub fn with_nodes(mut self, nodes: &[&str]) -> Self{
+        if nodes.is_empty(){
+            return self;
+        }
+        self.nodes = nodes.iter().map(|n| n.to_string()).collect();
+        self.current = 0;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: FailoverStrategy) -> Self{
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn send_options(mut self, send_options: SendOptions) -> Self{
+        self.send_options = send_options;
+        self
+    }
+
+    ///
+    /// Set the minimum time between two checks of the primary node, with the PrimaryBackup strategy
+    ///
+    pub fn primary_cooldown(mut self, cooldown: Duration) -> Self{
+        self.primary_cooldown = cooldown;
+        self
+    }
+
+    pub fn network(&self) -> Network {
+        self.network.clone()
+    }
+
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    pub fn failover_strategy(&self) -> FailoverStrategy {
+        self.strategy
+    }
+
+    pub fn current(&self) -> &str {
+        &self.nodes[self.current]
+    }
+
+    ///
+    /// Create a new client connected to the current node
+    ///
+    pub fn client(&self) -> StreamsClient{
+        let mut client = StreamsClient::new_from_url(self.current());
+        client.set_send_options(self.send_options.clone());
+        client
+    }
+
+    ///
+    /// Move to another healthy node according to the failover strategy
+    ///
+    /// # Return Value
+    /// It returns the url of the new node or None if there are no other healthy nodes.
+    ///
+    pub async fn next_healthy(&mut self) -> Option<String>{
+        let len = self.nodes.len();
+        let candidates: Vec<usize> = match self.strategy{
+            FailoverStrategy::RoundRobin => (1..len).map(|i| (self.current + i) % len).collect(),
+            FailoverStrategy::PrimaryBackup => (0..len).filter(|i| *i != self.current).collect()
+        };
+
+        for idx in candidates {
+            if NodePool::is_healthy(&self.nodes[idx]).await{
+                self.current = idx;
+                self.last_primary_check = Some(Instant::now());
+                return Some(self.nodes[idx].clone());
+            }
+        }
+        None
+    }
+
+    ///
+    /// With the PrimaryBackup strategy, go back to the primary node if it is healthy again.
+    /// The primary node is checked at most once per cooldown, starting from the failover
+    ///
+    /// # Return Value
+    /// It returns true if the pool moved back to the primary node
+    ///
+    pub async fn restore_primary(&mut self) -> bool{
+        if self.strategy != FailoverStrategy::PrimaryBackup || self.current == 0{
+            return false;
+        }
+        if let Some(last_check) = self.last_primary_check{
+            if last_check.elapsed() < self.primary_cooldown{
+                return false;
+            }
+        }
+
+        self.last_primary_check = Some(Instant::now());
+        if !NodePool::is_healthy(&self.nodes[0]).await{
+            return false;
+        }
+        self.current = 0;
+        true
+    }
+}
+
+impl Default for NodePool{
+    fn default() -> Self {
+        NodePool::from_network(&Network::default())
+    }
+}
+
+///
+/// Await the given operation with the retry policy of the channel and, if it still fails,
+/// switch to another node of the pool and try again.
+/// With the PrimaryBackup strategy the channel goes back to the primary node first, when it is healthy again.
+///
+macro_rules! with_failover {
+    ($channel:ident, $op:expr) => {{
+        $channel.restore_primary().await;
+        match with_retry!($channel.retry_policy, $op){
+            Ok(res) => Ok(res),
+            Err(e) => match $channel.failover().await{
+                Ok(true) => with_retry!($channel.retry_policy, $op),
+                Ok(false) => Err(e),
+                Err(failover_err) => Err(failover_err)
+            }
+        }
+    }};
+}
+pub(crate) use with_failover;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup_pool() -> NodePool{
+        let mut pool = NodePool::new(&["http://primary", "http://backup"], FailoverStrategy::PrimaryBackup)
+            .primary_cooldown(Duration::from_secs(3600));
+        pool.current = 1;
+        pool.last_primary_check = Some(Instant::now());
+        pool
+    }
+
+    #[test]
+    fn nodes_keep_the_network() {
+        let pool = NodePool::from_network(&Network::Mainnet).with_nodes(&["http://node"]);
+        assert_eq!(pool.network(), Network::Mainnet);
+        assert_eq!(pool.current(), "http://node");
+    }
+
+    #[tokio::test]
+    async fn primary_is_not_checked_during_the_cooldown() {
+        let mut pool = backup_pool();
+        assert!(!pool.restore_primary().await);
+        assert_eq!(pool.current(), "http://backup");
+    }
+
+    #[tokio::test]
+    async fn round_robin_pool_stays_on_the_current_node() {
+        let mut pool = backup_pool().strategy(FailoverStrategy::RoundRobin).primary_cooldown(Duration::from_secs(0));
+        assert!(!pool.restore_primary().await);
+        assert_eq!(pool.current(), "http://backup");
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use fs2::FileExt;
+
+///
+/// Exclusive lock on a file, shared by all the processes. It is released when dropped
+///
+pub struct FileLock{
+    file: File,
+}
+
+impl Drop for FileLock{
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+///
+/// Take the exclusive lock of a file, using the `<file_path>.lock` file.
+/// It fails immediately if another process holds the lock
+///
+pub fn lock_file(file_path: &str) -> Result<FileLock>{
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(lock_path(file_path))?;
+
+    match file.try_lock_exclusive(){
+        Ok(_) => Ok(FileLock{ file }),
+        Err(_) => Err(anyhow::Error::msg(format!("The file {} is locked by another process", file_path)))
+    }
+}
+
+///
+/// Get the path of a backup generation: `<file_path>.1` is the most recent one
+///
+pub fn backup_path(file_path: &str, generation: usize) -> String{
+    format!("{}.{}", file_path, generation)
+}
+
+///
+/// Get the backup generations of a file that exist, from the most recent one.
+/// There can be gaps, i.e. when a corrupted backup has been deleted
+///
+pub fn backup_generations(file_path: &str) -> Result<Vec<usize>>{
+    let path = Path::new(file_path);
+    let name = match path.file_name().and_then(|name| name.to_str()){
+        Some(name) => format!("{}.", name),
+        None => return Err(anyhow::Error::msg(format!("Invalid file path: {}", file_path)))
+    };
+    let dir = match path.parent(){
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+
+    let mut generations = vec![];
+    for entry in fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        let generation = file_name.to_str()
+            .and_then(|file_name| file_name.strip_prefix(&name))
+            .filter(|suffix| !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|suffix| suffix.parse::<usize>().ok());
+        if let Some(generation) = generation.filter(|generation| *generation > 0){
+            generations.push(generation);
+        }
+    }
+    generations.sort_unstable();
+    Ok(generations)
+}
+
+///
+/// Remove a file with all its backup generations, its temporary file and its lock file.
+/// It does nothing for the files that don't exist
+///
+pub fn remove_with_backups(file_path: &str) -> Result<()>{
+    {
+        let _lock = lock_file(file_path)?;
+        remove_if_exists(Path::new(file_path))?;
+        for generation in backup_generations(file_path)? {
+            remove_if_exists(Path::new(&backup_path(file_path, generation)))?;
+        }
+        remove_if_exists(&temp_path(Path::new(file_path))?)?;
+    }
+    remove_if_exists(Path::new(&lock_path(file_path)))
+}
+
+///
+/// Replace the content of a file so that, even if the process crashes, the file contains either the old or the new content.
+/// The data is written in a temporary file that is synced and renamed over the target.
+/// The previous content is kept in up to `backups` generations of backup files.
+///
+pub fn atomic_write(file_path: &str, data: &[u8], backups: usize) -> Result<()>{
+    let path = Path::new(file_path);
+    let tmp_path = temp_path(path)?;
+
+    {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .create(true)
+            .open(&tmp_path)?;
+        tmp.write_all(data)?;
+        tmp.sync_all()?;
+    }
+
+    if backups > 0 && path.exists(){
+        rotate_backups(file_path, backups)?;
+    }
+
+    fs::rename(&tmp_path, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+fn temp_path(path: &Path) -> Result<PathBuf>{
+    match path.file_name().and_then(|name| name.to_str()){
+        Some(name) => Ok(path.with_file_name(format!(".{}.tmp", name))),
+        None => Err(anyhow::Error::msg(format!("Invalid file path: {}", path.display())))
+    }
+}
+
+fn rotate_backups(file_path: &str, backups: usize) -> Result<()>{
+    for generation in (1..backups).rev() {
+        let older = backup_path(file_path, generation);
+        if Path::new(&older).exists(){
+            fs::rename(&older, backup_path(file_path, generation + 1))?;
+        }
+    }
+
+    // The current file is copied and not renamed, so it is never missing
+    let newest = backup_path(file_path, 1);
+    fs::copy(file_path, &newest)?;
+    // On Windows a file can be synced only through a handle with write access
+    OpenOptions::new().write(true).open(&newest)?.sync_all()?;
+    Ok(())
+}
+
+fn lock_path(file_path: &str) -> String{
+    format!("{}.lock", file_path)
+}
+
+fn remove_if_exists(path: &Path) -> Result<()>{
+    match fs::remove_file(path){
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into())
+    }
+}
+
+fn sync_parent_dir(path: &Path){
+    let dir = match path.parent(){
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+    // Directories can't be opened on every platform: the rename is already done anyway
+    if let Ok(dir) = File::open(dir){
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_write_rotates_the_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("state");
+        let file_path = file_path.to_str().unwrap();
+        for data in &["first", "second", "third", "fourth"] {
+            atomic_write(file_path, data.as_bytes(), 2).unwrap();
+        }
+
+        assert_eq!(fs::read(file_path).unwrap(), b"fourth");
+        assert_eq!(fs::read(backup_path(file_path, 1)).unwrap(), b"third");
+        assert_eq!(fs::read(backup_path(file_path, 2)).unwrap(), b"second");
+        assert_eq!(backup_generations(file_path).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn backup_generations_skip_gaps_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("state");
+        let file_path = file_path.to_str().unwrap();
+        for name in &["state.1", "state.3", "state.lock", "state.x", "other.2"] {
+            fs::write(dir.path().join(name), b"data").unwrap();
+        }
+        assert_eq!(backup_generations(file_path).unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn remove_with_backups_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("state");
+        let file_path = file_path.to_str().unwrap();
+        {
+            let _lock = lock_file(file_path).unwrap();
+            for data in &["first", "second", "third"] {
+                atomic_write(file_path, data.as_bytes(), 2).unwrap();
+            }
+        }
+
+        remove_with_backups(file_path).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+        remove_with_backups(file_path).unwrap();
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("state");
+        let file_path = file_path.to_str().unwrap();
+        let lock = lock_file(file_path).unwrap();
+        assert!(lock_file(file_path).is_err());
+        drop(lock);
+        assert!(lock_file(file_path).is_ok());
+    }
+}
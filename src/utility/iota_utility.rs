@@ -11,13 +11,17 @@ use crypto::hashes::{
     blake2b::Blake2b256
 };
 use std::str::FromStr;
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+use zeroize::Zeroizing;
+use iota_streams::core_edsig::signature::ed25519::PublicKey;
+
+const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ9";
+const SEED_LEN: usize = 81;
 
 ///
 /// Generates a new random String of 81 Chars of A..Z and 9
 ///
 pub fn random_seed() -> String {
-    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ9";
-    const SEED_LEN: usize = 81;
     let mut rng = rand::thread_rng();
 
     let seed: String = (0..SEED_LEN)
@@ -30,6 +34,63 @@ pub fn random_seed() -> String {
     seed
 }
 
+///
+/// Generates a new random BIP39 mnemonic of 24 english words
+///
+pub fn random_mnemonic() -> String {
+    Mnemonic::new(MnemonicType::Words24, Language::English).phrase().to_string()
+}
+
+///
+/// Converts a BIP39 mnemonic, with an optional passphrase, into a seed of 81 Chars of A..Z and 9.
+/// The same mnemonic and passphrase always generate the same seed
+///
+pub fn mnemonic_to_seed(mnemonic: &str, passphrase: Option<&str>) -> Result<String> {
+    let mnemonic = match Mnemonic::from_phrase(mnemonic.trim(), Language::English){
+        Ok(m) => m,
+        Err(e) => return Err(anyhow::Error::msg(format!("Invalid mnemonic: {}", e)))
+    };
+    let bip39_seed = Seed::new(&mnemonic, passphrase.unwrap_or(""));
+    Ok(bytes_to_seed(bip39_seed.as_bytes()))
+}
+
+///
+/// Derives the seed of a device from a master secret and a path of non-empty levels separated by `/`
+/// (e.g. `fleet/site-3/device-42`). Each level is derived from the previous one,
+/// so the same master secret and path always give the same seed
+///
+pub fn derive_seed(master: &str, path: &str) -> Result<String> {
+    let levels: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if levels.iter().any(|level| level.is_empty()) {
+        return Err(anyhow::Error::msg(format!("Invalid derivation path: {}", path)));
+    }
+
+    let mut key = Blake2b256::digest(master.as_bytes()).to_vec();
+    for level in levels {
+        key = Blake2b256::digest(&[&key[..], b"/", level.as_bytes()].concat()).to_vec();
+    }
+    Ok(bytes_to_seed(&key))
+}
+
+///
+/// Deterministically maps a secret into a seed of 81 Chars of A..Z and 9
+///
+fn bytes_to_seed(secret: &[u8]) -> String {
+    let mut seed = String::with_capacity(SEED_LEN);
+    let mut counter: u32 = 0;
+    while seed.len() < SEED_LEN {
+        let block = Blake2b256::digest(&[secret, &counter.to_be_bytes()].concat());
+        // 243 is the greatest multiple of the charset length: greater bytes are discarded to avoid bias
+        for b in block.iter().filter(|b| **b < 243) {
+            if seed.len() < SEED_LEN {
+                seed.push(CHARSET[(*b as usize) % CHARSET.len()] as char);
+            }
+        }
+        counter += 1;
+    }
+    seed
+}
+
 ///
 /// Generates SendOptions struct with the specified mwm and pow
 ///
@@ -55,12 +116,15 @@ pub fn create_encryption_nonce(string_nonce: &str) -> [u8;24]{
     hash_string(string_nonce).as_bytes()[..24].try_into().unwrap()
 }
 
-pub fn decrypt_data(data: &[u8], key: &[u8; 32], nonce: &[u8; 24]) -> Result<Vec<u8>>{
+///
+/// Decrypt the data. The decrypted data is wiped from memory when dropped
+///
+pub fn decrypt_data(data: &[u8], key: &[u8; 32], nonce: &[u8; 24]) -> Result<Zeroizing<Vec<u8>>>{
     let key_arr = GenericArray::from_slice(key);
     let nonce_arr = GenericArray::from_slice(nonce);
     let chacha = XChaCha20Poly1305::new(key_arr);
     match chacha.decrypt(nonce_arr, data.as_ref()){
-        Ok(dec) => Ok(dec),
+        Ok(dec) => Ok(Zeroizing::new(dec)),
         Err(_) => return Err(anyhow::Error::msg("Error during data decryption"))
     }
 }
@@ -80,3 +144,91 @@ pub fn msg_index(address: &Address) -> String{
     let hash = Blake2b256::digest(&total);
     hex::encode(&hash)
 }
+
+///
+/// Get the hex encoding of an Ed25519 public key
+///
+pub fn public_key_hex(public_key: &PublicKey) -> String{
+    hex::encode(public_key.as_bytes())
+}
+
+///
+/// Get the fingerprint of an Ed25519 public key: the hex encoding of the first 16 bytes of its blake2b digest
+///
+pub fn public_key_fingerprint(public_key: &PublicKey) -> String{
+    key_bytes_fingerprint(public_key.as_bytes())
+}
+
+///
+/// Check if a public key matches a pinned key, given as hex encoded public key or fingerprint
+///
+pub fn public_key_matches(public_key: &PublicKey, pinned: &str) -> bool{
+    public_key_hex_matches(&public_key_hex(public_key), pinned)
+}
+
+///
+/// Check if a hex encoded public key matches a pinned key, given as hex encoded public key or fingerprint
+///
+pub fn public_key_hex_matches(public_key: &str, pinned: &str) -> bool{
+    let pinned = pinned.trim().to_lowercase();
+    let public_key = public_key.to_lowercase();
+    if pinned == public_key{
+        return true;
+    }
+    match hex::decode(&public_key){
+        Ok(bytes) => pinned == key_bytes_fingerprint(&bytes),
+        Err(_) => false
+    }
+}
+
+fn key_bytes_fingerprint(bytes: &[u8]) -> String{
+    let hash = Blake2b256::digest(bytes);
+    hex::encode(&hash[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_seed(seed: &str) -> bool{
+        seed.len() == SEED_LEN && seed.bytes().all(|b| CHARSET.contains(&b))
+    }
+
+    #[test]
+    fn mnemonic_always_gives_the_same_seed() {
+        let mnemonic = random_mnemonic();
+        let seed = mnemonic_to_seed(&mnemonic, None).unwrap();
+        assert!(is_seed(&seed));
+        assert_eq!(mnemonic_to_seed(&format!(" {} ", mnemonic), None).unwrap(), seed);
+        assert_ne!(mnemonic_to_seed(&mnemonic, Some("passphrase")).unwrap(), seed);
+    }
+
+    #[test]
+    fn invalid_mnemonic_is_rejected() {
+        assert!(mnemonic_to_seed("not a mnemonic", None).is_err());
+        let mnemonic = random_mnemonic();
+        let words: Vec<&str> = mnemonic.split(' ').collect();
+        assert!(mnemonic_to_seed(&words[..23].join(" "), None).is_err());
+    }
+
+    #[test]
+    fn derived_seed_depends_on_the_master_and_the_path() {
+        let seed = derive_seed("master", "fleet/site-3/device-42").unwrap();
+        assert!(is_seed(&seed));
+        assert_eq!(derive_seed("master", "/fleet/site-3/device-42/").unwrap(), seed);
+        assert_ne!(derive_seed("other", "fleet/site-3/device-42").unwrap(), seed);
+        assert_ne!(derive_seed("master", "fleet/site-3/device-43").unwrap(), seed);
+        assert_ne!(derive_seed("master", "fleet/site-3").unwrap(), seed);
+    }
+
+    #[test]
+    fn levels_are_not_mixed() {
+        assert_ne!(derive_seed("master", "ab/c").unwrap(), derive_seed("master", "a/bc").unwrap());
+    }
+
+    #[test]
+    fn empty_levels_are_rejected() {
+        assert!(derive_seed("master", "").is_err());
+        assert!(derive_seed("master", "fleet//device-42").is_err());
+    }
+}
@@ -1 +1,4 @@
 pub mod iota_utility;
+pub mod retry_policy;
+pub mod secrets;
+pub mod file_utility;
@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+///
+/// Policy used to retry the operations that fail because of a transient node error.
+/// The delay between attempts grows exponentially from `base_delay` up to `max_delay`.
+/// The delays are awaited with `tokio::time::sleep`, so a policy with more than one attempt needs a Tokio runtime.
+///
+#[derive(Clone, Copy)]
+pub struct RetryPolicy{
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retryable: fn(&anyhow::Error) -> bool,
+}
+
+impl RetryPolicy{
+    ///
+    /// Policy with 3 attempts that retries every error: use `retryable` to retry only the transient ones
+    ///
+    pub fn new() -> RetryPolicy{
+        RetryPolicy{
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            retryable: |_| true,
+        }
+    }
+
+    ///
+    /// Policy that never retries: every error is returned immediately
+    ///
+    pub fn no_retry() -> RetryPolicy{
+        RetryPolicy::new().max_attempts(1)
+    }
+}
+
+impl RetryPolicy{
+    ///
+    /// Total number of attempts, the first one included
+    ///
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self{
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self{
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self{
+        self.max_delay = max_delay;
+        self
+    }
+
+    ///
+    /// If enabled each delay is randomly chosen between half and the whole computed backoff
+    ///
+    pub fn jitter(mut self, jitter: bool) -> Self{
+        self.jitter = jitter;
+        self
+    }
+
+    ///
+    /// Set the function that decides if an error is transient and so the operation can be retried
+    ///
+    pub fn retryable(mut self, retryable: fn(&anyhow::Error) -> bool) -> Self{
+        self.retryable = retryable;
+        self
+    }
+
+    ///
+    /// Get the delay to wait before the next attempt
+    ///
+    /// # Return Value
+    /// It returns None if the error is not retryable or there are no attempts left.
+    /// `attempt` is the number of the failed attempts before the current one.
+    ///
+    pub fn next_delay(&self, attempt: u32, err: &anyhow::Error) -> Option<Duration>{
+        if attempt + 1 >= self.max_attempts || !(self.retryable)(err){
+            return None;
+        }
+
+        let factor = 2u32.saturating_pow(attempt);
+        let backoff = self.base_delay.checked_mul(factor).unwrap_or(self.max_delay).min(self.max_delay);
+        if !self.jitter || backoff.as_millis() == 0{
+            return Some(backoff);
+        }
+
+        let millis = backoff.as_millis() as u64;
+        let delay = rand::thread_rng().gen_range(millis / 2..=millis);
+        Some(Duration::from_millis(delay))
+    }
+}
+
+///
+/// The default policy never retries, since not every error is transient and no runtime is required
+///
+impl Default for RetryPolicy{
+    fn default() -> Self {
+        RetryPolicy::no_retry()
+    }
+}
+
+///
+/// Await the given operation until it succeeds or the policy stops retrying.
+/// The operation expression is evaluated again at each attempt.
+///
+macro_rules! with_retry {
+    ($policy:expr, $op:expr) => {{
+        let mut attempt = 0;
+        loop{
+            match $op.await{
+                Ok(res) => break Ok(res),
+                Err(e) => match $policy.next_delay(attempt, &e){
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => break Err(e)
+                }
+            }
+        }
+    }};
+}
+pub(crate) use with_retry;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_never_retries() {
+        let err = anyhow::Error::msg("error");
+        assert!(RetryPolicy::default().next_delay(0, &err).is_none());
+        assert!(RetryPolicy::new().next_delay(0, &err).is_some());
+    }
+
+    #[test]
+    fn not_retryable_errors_are_returned() {
+        let err = anyhow::Error::msg("Bad request");
+        let policy = RetryPolicy::new().retryable(|err| !err.to_string().contains("Bad request"));
+        assert!(policy.next_delay(0, &err).is_none());
+    }
+}
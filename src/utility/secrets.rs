@@ -0,0 +1,128 @@
+use std::fmt::{Debug, Formatter};
+
+use zeroize::Zeroize;
+
+use crate::utility::iota_utility::{create_encryption_key, create_encryption_nonce};
+
+///
+/// String containing a secret (i.e. a password). It is wiped from memory when dropped and never printed
+///
+#[derive(Clone, Zeroize)]
+#[zeroize(drop)]
+pub struct SecretString(String);
+
+impl SecretString{
+    pub fn new(secret: &str) -> SecretString{
+        SecretString(secret.to_string())
+    }
+
+    pub fn expose(&self) -> &str{
+        &self.0
+    }
+}
+
+impl From<&str> for SecretString{
+    fn from(secret: &str) -> Self {
+        SecretString::new(secret)
+    }
+}
+
+impl From<String> for SecretString{
+    fn from(secret: String) -> Self {
+        SecretString(secret)
+    }
+}
+
+impl From<&String> for SecretString{
+    fn from(secret: &String) -> Self {
+        SecretString::new(secret)
+    }
+}
+
+impl From<&SecretString> for SecretString{
+    fn from(secret: &SecretString) -> Self {
+        secret.clone()
+    }
+}
+
+impl Debug for SecretString{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretString(***)")
+    }
+}
+
+///
+/// Key and nonce used to encrypt the masked part of the packets. They are wiped from memory when dropped and never printed
+///
+#[derive(Clone, Zeroize)]
+#[zeroize(drop)]
+pub struct SymmetricKey{
+    key: [u8; 32],
+    nonce: [u8; 24],
+}
+
+impl SymmetricKey{
+    pub fn new(key: &[u8; 32], nonce: &[u8; 24]) -> SymmetricKey{
+        SymmetricKey{
+            key: *key,
+            nonce: *nonce,
+        }
+    }
+
+    ///
+    /// Create the key and the nonce starting from two secret strings
+    ///
+    pub fn from_secrets(string_key: &str, string_nonce: &str) -> SymmetricKey{
+        let mut key = create_encryption_key(string_key);
+        let mut nonce = create_encryption_nonce(string_nonce);
+        let res = SymmetricKey::new(&key, &nonce);
+        key.zeroize();
+        nonce.zeroize();
+        res
+    }
+
+    pub fn key(&self) -> &[u8; 32]{
+        &self.key
+    }
+
+    pub fn nonce(&self) -> &[u8; 24]{
+        &self.nonce
+    }
+}
+
+impl From<([u8; 32], [u8; 24])> for SymmetricKey{
+    fn from(key_nonce: ([u8; 32], [u8; 24])) -> Self {
+        let (mut key, mut nonce) = key_nonce;
+        let res = SymmetricKey::new(&key, &nonce);
+        key.zeroize();
+        nonce.zeroize();
+        res
+    }
+}
+
+impl Debug for SymmetricKey{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SymmetricKey(***)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expose_into(secret: impl Into<SecretString>) -> String{
+        secret.into().expose().to_string()
+    }
+
+    #[test]
+    fn secrets_are_converted_and_redacted() {
+        let owned = "psw".to_string();
+        let secret = SecretString::new("psw");
+        assert_eq!(expose_into("psw"), "psw");
+        assert_eq!(expose_into(&owned), "psw");
+        assert_eq!(expose_into(&secret), "psw");
+        assert_eq!(expose_into(owned), "psw");
+        assert_eq!(format!("{:?}", secret), "SecretString(***)");
+        assert_eq!(format!("{:?}", SymmetricKey::from_secrets("key", "nonce")), "SymmetricKey(***)");
+    }
+}